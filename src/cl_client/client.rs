@@ -1,10 +1,11 @@
-use star_realms::game::{UserActionSupplier, Feedback, GameState, UserActionIntent, RelativePlayer, AbstractPlayerAction};
+use star_realms::game::{UserActionSupplier, Feedback, GameState, UserActionIntent, RelativePlayer, AbstractPlayerAction, GameEvent, AttackTarget, CombatAction, Player};
 use std::collections::{HashSet, HashMap};
-use star_realms::game::effects::{ConfigSupplier, Config, ActionConfigMethod, get_condition};
+use star_realms::game::effects::{ConfigSupplier, Config, ActionConfigMethod, get_trigger, encode_arrangement};
 use std::io;
 use std::str::FromStr;
 use ansi_term::Color;
 use star_realms::game::components::card::CardStatus;
+use star_realms::game::components::faction::{Faction, Factions};
 
 pub struct Client {
     pub name: String
@@ -62,27 +63,56 @@ impl Client {
 }
 impl UserActionSupplier for Client {
     fn choose_abstract_action(&self, game: &GameState) -> AbstractPlayerAction {
-        println!("Select an action:");
+        println!("{:?} phase. Select an action:", game.get_phase());
         let options = vec![
             "Use effects on cards",
+            "Play all free effects",
             "View trade row",
             "Trash a card",
             "End Turn",
-            "View cards in hand"
+            "Undo",
+            if game.is_auto_play() { "Turn off auto play" } else { "Turn on auto play" },
+            "Target an opponent",
+            "Reveal a mission",
+            "View cards in hand",
+            "View scrap heap"
             ];
         print_options(&options);
         match get_value_input(|&i: &u8| i < options.len() as u8) {
             0 => AbstractPlayerAction::CardEffects,
-            1 => AbstractPlayerAction::TradeRow,
-            2 => AbstractPlayerAction::TrashCard,
-            3 => AbstractPlayerAction::EndTurn,
-            4 | _ => {
+            1 => AbstractPlayerAction::PlayAllFree,
+            2 => AbstractPlayerAction::TradeRow,
+            3 => AbstractPlayerAction::TrashCard,
+            4 => AbstractPlayerAction::EndTurn,
+            5 => AbstractPlayerAction::Undo,
+            6 => AbstractPlayerAction::ToggleAutoPlay,
+            7 => AbstractPlayerAction::TargetOpponent,
+            8 => AbstractPlayerAction::RevealMission,
+            9 => {
                 // this is mostly a debug option, so it's not going to have any real implementation
                 let ids = game.get_current_player().get_all_hand_card_ids().clone();
                 let player = game.get_current_player();
                 for id in ids {
-                    let (card, _) = player.get_card_in_hand(&id).unwrap();
-                    println!(" - {}: {}", id, card.name);
+                    let (card, card_status) = player.get_card_in_hand(&id).unwrap();
+                    println!(" - {}: {}{}", id, card.name, format_factions(game.card_library.get_factions(), &card_status.factions(card)));
+                }
+                for id in player.get_gambit_ids() {
+                    let (card, _) = player.get_card(&id).unwrap();
+                    println!(" - {}: {} (gambit)", id, card.name);
+                }
+                for id in player.get_hero_ids() {
+                    let (card, card_status) = player.get_card(&id).unwrap();
+                    println!(" - {}: {}{} (hero)", id, card.name, format_factions(game.card_library.get_factions(), &card_status.factions(card)));
+                }
+                self.choose_abstract_action(game)
+            }
+            _ => {
+                let heap = game.get_scrap_heap();
+                if heap.is_empty() {
+                    println!("Nothing has been scrapped yet");
+                }
+                for record in heap.iter() {
+                    println!(" - {} (scrapped by {} from {:?})", record.card.name, record.by, record.from);
                 }
                 self.choose_abstract_action(game)
             }
@@ -92,15 +122,18 @@ impl UserActionSupplier for Client {
         println!("Select an action:");
         let mut index = 1;
         let cp = game.get_current_player();
-        let ids = cp.get_all_hand_card_ids();
+        let ids = cp.get_all_card_ids();
         let mut enumerated = HashMap::new();
         let mut card_index_map = HashMap::new();
 
         println!(" {}: Skip effects", Color::Blue.paint("0"));
-        for (id, (card, card_status)) in ids.iter().map(|id| (id, cp.get_card_in_hand(id).unwrap())) {
-            let unused_effects = card_status.unused_effects(card);
+        for (id, (card, card_status)) in ids.iter().map(|id| (id, cp.get_card(id).unwrap())) {
+            // triggered effects are resolved by the game, they can't be picked
+            let unused_effects: Vec<_> = card_status.unused_effects(card).into_iter()
+                .filter(|(cond, _)| get_trigger(cond).is_none())
+                .collect();
             if !unused_effects.is_empty() {
-                println!("{}:", &card.name);
+                println!("{}{}:", &card.name, format_factions(game.card_library.get_factions(), &card_status.factions(card)));
                 // println!("All effects: {:?}", card.effects);
                 for effect in unused_effects {
                     if CardStatus::is_free(&effect.1) {
@@ -138,9 +171,9 @@ impl UserActionSupplier for Client {
             if *index == 0 {
                 return UserActionIntent::Cancel;
             }
-            let id = card_index_map.get(index).unwrap().clone();
+            let id = *card_index_map.get(index).unwrap();
             let e = enumerated.get(index).unwrap().clone();
-            if !(get_condition(e.0.clone()).unwrap())(game, id) {
+            if !(game.card_library.get_effects().get_condition(&e.0).unwrap())(game, id) {
                 println!("The condition {} was not met. Please try a different effect", &e.0);
                 continue;
             } else {
@@ -154,16 +187,97 @@ impl UserActionSupplier for Client {
         if game.explorers > 0 {
             println!(" {} - explorer ({} left)", Color::Blue.paint("0"), game.explorers);
         }
-        let mut index = 1;
-        for id in game.trade_row.elements.iter() {
+        for (index, id) in (1..).zip(game.trade_row.elements.iter()) {
             let card = game.card_library.as_card(id);
-            println!(" {} - {} ({})", Color::Blue.paint(index.to_string()), card.name, Color::Yellow.paint(card.cost.to_string()));
-            index += 1;
+            println!(" {} - {}{} ({})", Color::Blue.paint(index.to_string()), card.name,
+                     if card.is_hero() { " [hero]" } else { "" }, Color::Yellow.paint(card.cost.to_string()));
         }
         UserActionIntent::Continue(get_value_input(|&i| {
             i <= game.trade_row.len() as u32 && (i > 0 || game.explorers > 0)
         }))
     }
+    fn select_mission(&self, game: &GameState) -> UserActionIntent<usize> {
+        let player = game.get_current_player();
+        println!("Completed missions: {}", player.get_completed_missions().len());
+        println!(" {} - Cancel", Color::Blue.paint("0"));
+        for (index, mission) in (1..).zip(player.get_missions().iter()) {
+            let met = if game.mission_objective_met(index - 1) { " (ready!)" } else { "" };
+            println!(" {} - {}: {} => {}{}", Color::Blue.paint(index.to_string()), mission.name,
+                     mission.objective, mission.reward, met);
+        }
+        match get_value_input(|&i: &usize| i <= player.get_missions().len()) {
+            0 => UserActionIntent::Cancel,
+            i => UserActionIntent::Continue(i - 1)
+        }
+    }
+    fn select_opponent(&self, game: &GameState) -> UserActionIntent<Player> {
+        let opponents = game.targetable_opponents();
+        println!("Currently targeting {}. Pick an opponent:", game.get_target());
+        println!(" {} - Cancel", Color::Blue.paint("0"));
+        for (index, opponent) in (1..).zip(opponents.iter()) {
+            println!(" {} - {} ({} authority)", Color::Blue.paint(index.to_string()), opponent,
+                     game.authority_of(*opponent));
+        }
+        match get_value_input(|&i: &usize| i <= opponents.len()) {
+            0 => UserActionIntent::Cancel,
+            i => UserActionIntent::Continue(opponents[i - 1])
+        }
+    }
+    fn select_combat_action(&self, game: &GameState) -> UserActionIntent<CombatAction> {
+        println!("Combat phase: {}", game.get_current_player().get_goods());
+        println!(" {} - Stop attacking", Color::Blue.paint("0"));
+        let can_undo = game.can_undo();
+        if can_undo {
+            println!(" {} - Undo", Color::Blue.paint("1"));
+        }
+        let mut options = HashMap::new();
+        let mut index = 2;
+        for player in game.attackable_authorities() {
+            if !game.get_player(player).has_outpost_in_play() {
+                println!(" {} - Attack {}'s authority ({})", Color::Blue.paint(index.to_string()), player,
+                         game.authority_of(player));
+                options.insert(index, AttackTarget::Authority(player));
+                index += 1;
+            }
+        }
+        for player in game.attackable_base_owners() {
+            let opponent = game.get_player(player);
+            for id in opponent.targetable_bases(true) {
+                let (card, _) = opponent.get_card_in_hand(&id).unwrap();
+                println!(" {} - Attack {}'s {} ({} defense)", Color::Blue.paint(index.to_string()), player,
+                         card.name, card.base.as_ref().unwrap().defense());
+                options.insert(index, AttackTarget::Base(player, id));
+                index += 1;
+            }
+        }
+        match get_value_input(|i: &u32| *i == 0 || (*i == 1 && can_undo) || options.contains_key(i)) {
+            0 => UserActionIntent::Cancel,
+            1 => UserActionIntent::Continue(CombatAction::Undo),
+            i => UserActionIntent::Continue(CombatAction::Attack(options[&i]))
+        }
+    }
+    fn on_event(&self, event: &GameEvent) {
+        match event {
+            GameEvent::BaseDestroyed { owner, by, base } =>
+                println!("{}", Color::Red.paint(format!("{} destroyed {}, a base of {}", by, base.name, owner))),
+            GameEvent::MissionCompleted { player, mission } =>
+                println!("{}", Color::Green.paint(format!("{} completed the mission {}: {}", player, mission.name, mission.objective))),
+            GameEvent::PlayerEliminated { player, by } =>
+                println!("{}", Color::Red.paint(format!("{} was eliminated by {}", player, by))),
+            GameEvent::EventResolved { card, skipped } => {
+                let mut effects: Vec<_> = card.effects.iter().map(|(_, act)| act.as_str()).collect();
+                effects.sort_unstable();
+                println!("{}", Color::Purple.paint(format!("Event {} was revealed: {}", card.name, effects.join(", "))));
+                for msg in skipped {
+                    println!("{}", Color::Red.paint(msg));
+                }
+            }
+        }
+    }
+    fn confirm(&self, _: &GameState, question: &str) -> bool {
+        println!("{} (y/n)", question);
+        input().as_str() == "y"
+    }
     fn on_feedback(&self, feedback: Feedback) {
         match feedback {
             Feedback::Invalid(msg) => println!("{} {}", Color::Red.paint("Invalid action!"), Color::Red.paint(msg)),
@@ -175,16 +289,58 @@ impl UserActionSupplier for Client {
     }
 }
 
-fn print_options<T: ToString>(options: &Vec<T>) {
+/// ex. " [Blob, Star Empire]", or nothing if there are no factions
+fn format_factions(all: &Factions, factions: &HashSet<Faction>) -> String {
+    if factions.is_empty() {
+        return String::new();
+    }
+    let mut factions: Vec<_> = factions.iter().collect();
+    factions.sort_by_key(|f| all.name(f));
+    let names: Vec<_> = factions.into_iter().map(|f| paint_faction(all, f)).collect();
+    format!(" [{}]", names.join(", "))
+}
+
+/// the faction's name in its colour
+fn paint_faction(all: &Factions, faction: &Faction) -> String {
+    let name = all.name(faction);
+    match all.get(faction).and_then(|info| parse_colour(&info.colour)) {
+        Some(colour) => colour.paint(name).to_string(),
+        None => name
+    }
+}
+
+/// a colour name, ex. "blue", or a hex code, ex. "#ff8800"
+fn parse_colour(colour: &str) -> Option<Color> {
+    if let Some(hex) = colour.strip_prefix('#') {
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        return match hex.len() {
+            6 => Some(Color::RGB(channel(0)?, channel(2)?, channel(4)?)),
+            _ => None
+        };
+    }
+    match colour.to_lowercase().as_str() {
+        "black" => Some(Color::Black),
+        "red" => Some(Color::Red),
+        "green" => Some(Color::Green),
+        "yellow" => Some(Color::Yellow),
+        "blue" => Some(Color::Blue),
+        "purple" | "magenta" => Some(Color::Purple),
+        "cyan" => Some(Color::Cyan),
+        "white" => Some(Color::White),
+        _ => None
+    }
+}
+
+fn print_options<T: ToString>(options: &[T]) {
     for (index, element) in options.iter().enumerate() {
         println!(" {} - {}", Color::Blue.paint(index.to_string()), element.to_string());
     }
 }
 
-fn input() -> String {
+pub fn input() -> String {
     let mut s = String::new();
     io::stdin().read_line(&mut s).unwrap();
-    (&s[0..s.len()-1]).to_string()
+    s[0..s.len()-1].to_string()
 }
 
 /// Ensure that standard input results in a value that is satisfied by `valid`,
@@ -272,19 +428,102 @@ impl ConfigSupplier for Client {
                          input, out);
                 out
             },
+            ActionConfigMethod::PickTradeRowCard(by, filter) => {
+                let choices = game.trade_row_choices(filter);
+                if choices.is_empty() {
+                    println!("There is no {} in the trade row", filter.describe());
+                    return 0;
+                }
+                println!("{:?}, pick a {} from the trade row", by, filter.describe());
+                for &index in choices.iter() {
+                    let card = game.card_library.as_card(game.trade_row.peek(index).unwrap());
+                    println!(" {} - {} ({})", Color::Blue.paint(index.to_string()), card.name,
+                             Color::Yellow.paint(card.cost.to_string()));
+                }
+                get_value_input(|&i: &u32| choices.contains(&(i as usize)))
+            },
+            ActionConfigMethod::PickBase(by, owner, respect_outposts) => {
+                let player = game.resolve_relative_player(owner);
+                let bases = player.targetable_bases(*respect_outposts);
+                if bases.is_empty() {
+                    println!("There are no bases to pick");
+                    return 0;
+                }
+                println!("{:?}, pick one of {:?}'s bases", by, owner);
+                for id in bases.iter() {
+                    let (card, _) = player.get_card_in_hand(id).unwrap();
+                    println!(" {} - {}", Color::Blue.paint(id.to_string()), card.name);
+                }
+                get_value_input(|i| bases.contains(i))
+            },
+            ActionConfigMethod::PickFaction(by) => {
+                let factions = game.card_library.get_factions();
+                let all = factions.all();
+                println!("{:?}, pick a faction", by);
+                for (index, faction) in all.iter().enumerate() {
+                    println!(" {} - {}", Color::Blue.paint(index.to_string()),
+                             paint_faction(factions, faction));
+                }
+                get_value_input(|&i: &u32| (i as usize) < all.len())
+            },
+            ActionConfigMethod::PickScrappableCard(by) => {
+                let player = game.resolve_relative_player(by);
+                let ids = player.scrappable_card_ids();
+                println!("{:?}, pick a card to scrap", by);
+                for id in ids.iter() {
+                    let (card, _) = player.get_card(id).unwrap();
+                    println!(" {} - {}", Color::Blue.paint(id.to_string()), card.name);
+                }
+                get_value_input(|i| ids.contains(i))
+            },
+            ActionConfigMethod::ArrangeTopOfDeck(num, by) => {
+                let cards = game.resolve_relative_player(by).top_of_deck(*num as usize);
+                println!("{:?}, these are the top cards of your deck, from the top", by);
+                for (index, card) in cards.iter().enumerate() {
+                    println!(" {} - {}", Color::Blue.paint(index.to_string()), card.name);
+                }
+                println!("enter the cards to put back, top first (the rest are discarded), \
+                    or nothing to discard them all");
+                let order: Vec<usize> = ensure(
+                    input,
+                    |s| if s.trim().is_empty() { Ok(vec![]) } else { parse_vec(s) },
+                    |vs: &Vec<usize>| {
+                        let set: HashSet<_> = vs.iter().collect();
+                        set.len() == vs.len() && vs.iter().all(|&i| i < cards.len())
+                    },
+                    |_| println!("invalid input"),
+                    |s| println!("invalid input of '{:?}'", s)
+                );
+                encode_arrangement(&order)
+            },
+            ActionConfigMethod::PickDeckCard(by, filter) => {
+                let choices = game.deck_choices(by, filter);
+                if choices.is_empty() {
+                    println!("There is no {} in your deck", filter.describe());
+                    return 0;
+                }
+                let player = game.resolve_relative_player(by);
+                println!("{:?}, pick a {} from your deck", by, filter.describe());
+                for &index in choices.iter() {
+                    let card = player.get_card_in_deck(index).unwrap();
+                    println!(" {} - {} ({})", Color::Blue.paint(index.to_string()), card.name,
+                             Color::Yellow.paint(card.cost.to_string()));
+                }
+                get_value_input(|&i: &u32| choices.contains(&(i as usize)))
+            },
         };
         // my IDE can't handle this apparently lmao
         println!("{}\nAre you sure? (y/n)", (config.describe)(v).as_str());
         match input().as_str() {
             "y" => v,
-            "n" | _ => self.get_config(game, config)
+            _ => self.get_config(game, config)
         }
     }
 }
 
 
 /// because parsing doesn't exist for Vec<T: FromStr>???
-
+#[allow(clippy::result_unit_err)]
 pub fn parse_vec <T: FromStr> (input: &str) -> Result<Vec<T>, ()> {
     let split: Vec<_> = input.split(',').collect();
     let mut out = vec![];
@@ -5,15 +5,68 @@ pub mod test;
 pub mod main {
     extern crate star_realms;
 
-    use self::star_realms::game::{GameState};
-    use crate::cl_client::client::Client;
+    use self::star_realms::game::{GameState, GameFormat};
+    use self::star_realms::game::card_library::CardLibrary;
+    use crate::cl_client::client::{Client, get_value_input, input, parse_vec};
     use ansi_term::Color;
+    use std::rc::Rc;
 
     pub fn main () {
-        let mut game = match GameState::from_config("star_realms/config") {
+        let sets = match CardLibrary::available_sets("star_realms/config") {
+            Ok(sets) => sets,
+            Err(e) => panic!("Could not read the card sets: {}", e)
+        };
+        println!("Pick card sets (comma separated), or nothing for the default sets:");
+        for (index, set) in sets.iter().enumerate() {
+            println!(" {} - {}{}", index, set.name, if set.default { " (default)" } else { "" });
+        }
+        let card_library = loop {
+            let picked: Vec<&str> = match input().as_str() {
+                "" => sets.iter().filter(|set| set.default).map(|set| set.name.as_str()).collect(),
+                s => match parse_vec::<usize>(s) {
+                    Ok(indices) if indices.iter().all(|&i| i < sets.len()) =>
+                        indices.iter().map(|&i| sets[i].name.as_str()).collect(),
+                    _ => {
+                        println!("invalid input");
+                        continue;
+                    }
+                }
+            };
+            match CardLibrary::from_sets("star_realms/config", &picked) {
+                Ok(cl) => break Rc::new(cl),
+                Err(e) => println!("{}", Color::Red.paint(e))
+            }
+        };
+        println!("How many players? (2-4)");
+        let num_players = get_value_input(|n: &usize| (2..=4).contains(n));
+        let format = if num_players == 2 {
+            GameFormat::FreeForAll
+        } else {
+            println!("Pick a format: 0 - Free-for-all, 1 - Hunter, 2 - Raid{}",
+                     if num_players == 4 { ", 3 - Teams (2v2)" } else { "" });
+            match get_value_input(|n: &u8| *n < 3 || (*n == 3 && num_players == 4)) {
+                0 => GameFormat::FreeForAll,
+                1 => GameFormat::Hunter,
+                2 => GameFormat::Raid,
+                _ => GameFormat::Teams
+            }
+        };
+        let mut game = match GameState::with_players(card_library, num_players, format) {
             Ok(g) => g,
             Err(e) => panic!("Could not create game: {}", e)
         };
+        println!("Play with gambits? (y/n)");
+        if get_value_input(|s: &String| s == "y" || s == "n") == "y" {
+            if let Err(e) = game.deal_gambits(2) {
+                println!("{}", Color::Red.paint(e));
+            }
+        }
+        println!("Play with missions? (y/n)");
+        if get_value_input(|s: &String| s == "y" || s == "n") == "y" {
+            if let Err(e) = game.deal_missions(3) {
+                println!("{}", Color::Red.paint(e));
+            }
+        }
         let client = Client {
             name: "user".to_string()
         };
@@ -28,6 +81,11 @@ pub mod main {
                     break;
                 }
             }
+            if let Some(winners) = game.winners() {
+                let names: Vec<_> = winners.iter().map(|p| p.to_string()).collect();
+                println!("{} won!", names.join(" and "));
+                break;
+            }
         }
         println!("cl_client::main::main: Game has ended!");
     }
@@ -57,6 +115,9 @@ pub mod main {
                     break;
                 }
             }
+            if game.winners().is_some() {
+                break;
+            }
         }
         println!("cl_client::main::debug: Game has ended!");
     }
@@ -1,4 +1,4 @@
-use crate::cl_client::client::{get_value_input, parse_vec};
+use crate::cl_client::client::parse_vec;
 use ansi_term::Color;
 use ansi_term::Style;
 
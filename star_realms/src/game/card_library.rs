@@ -2,27 +2,134 @@ use crate::game::components::card::Card;
 use std::collections::HashMap;
 use crate::game::components::stack::Stack;
 use std::rc::Rc;
-use crate::parse::parse_file;
+use crate::parse::{parse_file, parse_missions_file, parse_set_manifest, parse_factions_file};
+use crate::game::components::mission::Mission;
+use crate::game::components::faction::Factions;
+use crate::game::effect_registry::EffectRegistry;
+use std::path::Path;
 
 pub struct CardLibrary {
     all_cards: Vec<Rc<Card>>,
     id_map: HashMap<u32, Rc<Card>>,
     id_lookup: HashMap<String, u32>,
-    trade_stack: Stack<u32>
+    trade_stack: Stack<u32>,
+    gambits: Vec<u32>,
+    missions: Vec<Mission>,
+    factions: Factions,
+    effects: EffectRegistry,
+}
+
+/// A set of cards (ex. the core set or an expansion), listed in `sets.yaml`.
+/// Files are relative to the config folder.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CardSet {
+    pub name: String,
+    /// whether the set is used when no sets are picked
+    pub default: bool,
+    /// cards that are shuffled into the trade deck
+    pub trade: Vec<String>,
+    /// cards outside of the trade deck (scouts, vipers and explorers)
+    pub misc: Vec<String>,
+    pub gambits: Vec<String>,
+    pub missions: Vec<String>,
+    /// factions the set's cards can have, on top of those from other sets
+    pub factions: Vec<String>,
 }
 
 impl CardLibrary {
+    /// Use the default sets in the folder's `sets.yaml`.
+    /// Without a `sets.yaml`, just `trade_cards.yaml` and `misc_cards.yaml` are used
     pub fn from_config(config_folder: &str) -> Result<CardLibrary, String> {
-        let trade_cards = parse_file(
-            format!("{}/trade_cards.yaml", config_folder)
-        )?;
-        let misc_cards = parse_file(
-            format!("{}/misc_cards.yaml", config_folder)
-        )?;
-        CardLibrary::new(trade_cards, misc_cards)
+        let sets: Vec<_> = CardLibrary::available_sets(config_folder)?.into_iter()
+            .filter(|set| set.default)
+            .collect();
+        CardLibrary::from_card_sets(config_folder, &sets)
+    }
+
+    /// every set that can be picked
+    pub fn available_sets(config_folder: &str) -> Result<Vec<CardSet>, String> {
+        let manifest = format!("{}/sets.yaml", config_folder);
+        if Path::new(&manifest).exists() {
+            parse_set_manifest(manifest)
+        } else {
+            Ok(vec![CardSet {
+                name: "core".to_string(),
+                default: true,
+                trade: vec!["trade_cards.yaml".to_string()],
+                misc: vec!["misc_cards.yaml".to_string()],
+                ..CardSet::default()
+            }])
+        }
+    }
+
+    /// Build a library out of the sets with these names.
+    /// Err if a set doesn't exist, or if two cards (or missions, or faction codes) are the same
+    pub fn from_sets(config_folder: &str, names: &[&str]) -> Result<CardLibrary, String> {
+        let available = CardLibrary::available_sets(config_folder)?;
+        let mut sets = vec![];
+        for name in names {
+            match available.iter().find(|set| set.name == *name) {
+                Some(set) => sets.push(set.clone()),
+                None => return Err(format!("There is no card set named '{}'", name))
+            }
+        }
+        CardLibrary::from_card_sets(config_folder, &sets)
+    }
+
+    fn from_card_sets(config_folder: &str, sets: &[CardSet]) -> Result<CardLibrary, String> {
+        let (mut trade_cards, mut misc_cards, mut gambits, mut missions) = (vec![], vec![], vec![], vec![]);
+        // where each name came from, to report conflicts
+        let mut origins: HashMap<String, String> = HashMap::new();
+        let mut conflicts = vec![];
+        let mut check = |name: &str, origin: String| {
+            if let Some(first) = origins.get(name) {
+                conflicts.push(format!("'{}' is in both {} and {}", name, first, origin));
+            } else {
+                origins.insert(name.to_string(), origin);
+            }
+        };
+        // factions come first, since cards are checked against them.
+        // without any factions file there are still the usual four
+        let mut factions = Factions::new();
+        for set in sets {
+            for file in set.factions.iter() {
+                for info in parse_factions_file(format!("{}/{}", config_folder, file))? {
+                    factions.add(info).map_err(|e| format!("Card sets conflict: {}", e))?;
+                }
+            }
+        }
+        if factions.all().is_empty() {
+            factions = Factions::core();
+        }
+        for set in sets {
+            for (files, cards) in [(&set.trade, &mut trade_cards), (&set.misc, &mut misc_cards),
+                                   (&set.gambits, &mut gambits)].iter_mut() {
+                for file in files.iter() {
+                    let parsed = parse_file(format!("{}/{}", config_folder, file), &factions)?;
+                    for card in parsed.iter() {
+                        check(&card.name, format!("{} ({})", set.name, file));
+                    }
+                    cards.extend(parsed);
+                }
+            }
+            for file in set.missions.iter() {
+                let parsed = parse_missions_file(format!("{}/{}", config_folder, file))?;
+                for mission in parsed.iter() {
+                    check(&mission.name, format!("{} ({})", set.name, file));
+                }
+                missions.extend(parsed);
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(format!("Card sets conflict: {}", conflicts.join(", ")));
+        }
+        let mut cl = CardLibrary::new(trade_cards, misc_cards, gambits)?;
+        cl.missions = missions;
+        cl.factions = factions;
+        Ok(cl)
     }
 
-    pub fn new(trade_stack: Vec<Card>, misc_cards: Vec<Card>) -> Result<CardLibrary, String> {
+    pub fn new(trade_stack: Vec<Card>, misc_cards: Vec<Card>, gambits: Vec<Card>) -> Result<CardLibrary, String> {
         let misc_cards = {
             let mut tmp = vec![];
             for card in misc_cards {
@@ -39,6 +146,8 @@ impl CardLibrary {
             tmp
         };
 
+        let gambits: Vec<Rc<Card>> = gambits.into_iter().map(Rc::new).collect();
+
         let all_cards: Vec<Rc<Card>> = {
             let mut ac = vec![];
             for card in trade_stack.iter() {
@@ -47,6 +156,9 @@ impl CardLibrary {
             for card in misc_cards.iter() {
                 ac.push(Rc::clone(card));
             }
+            for card in gambits.iter() {
+                ac.push(Rc::clone(card));
+            }
             ac
         };
 
@@ -55,7 +167,10 @@ impl CardLibrary {
             let mut id_lookup = HashMap::new();
             for (i, card) in all_cards.iter().enumerate() {
                 id_map.insert(i as u32, Rc::clone(card));
-                id_lookup.insert(card.name.clone(), i as u32);
+                // cards are looked up by name, so they can't share one
+                if id_lookup.insert(card.name.clone(), i as u32).is_some() {
+                    return Err(format!("There is more than one card named '{}'", card.name));
+                }
             }
             (id_map, id_lookup)
         };
@@ -68,10 +183,18 @@ impl CardLibrary {
             tmp
         };
 
+        let gambits = gambits.iter()
+            .map(|card| id_lookup[&card.name])
+            .collect();
+
         let cl = CardLibrary {
             id_map,
             id_lookup,
             trade_stack: ts,
+            gambits,
+            missions: vec![],
+            factions: Factions::core(),
+            effects: EffectRegistry::new(),
             all_cards
         };
         match cl.get_scout() {
@@ -94,6 +217,31 @@ impl CardLibrary {
         vec
     }
 
+    /// ids of every gambit that can be dealt
+    pub fn get_gambits(&self) -> Vec<u32> {
+        self.gambits.clone()
+    }
+
+    /// every mission that can be dealt
+    pub fn get_missions(&self) -> &[Mission] {
+        &self.missions
+    }
+
+    /// every faction the library's cards can have
+    pub fn get_factions(&self) -> &Factions {
+        &self.factions
+    }
+
+    /// every action and condition the library's cards can use
+    pub fn get_effects(&self) -> &EffectRegistry {
+        &self.effects
+    }
+
+    /// for registering more effects, before the library is used in a game
+    pub fn get_effects_mut(&mut self) -> &mut EffectRegistry {
+        &mut self.effects
+    }
+
     pub fn get_card_by_name(&self, name: &str) -> Option<Rc<Card>> {
         match self.id_lookup.get(name) {
             Some(id) => Some(self.get_card_by_id(id).unwrap()), // guarantee it, or else!
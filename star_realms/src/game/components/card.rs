@@ -1,4 +1,4 @@
-use crate::game::components::faction::{Faction, all_factions};
+use crate::game::components::faction::Faction;
 use std::collections::{HashSet};
 use crate::game::components::{Defense, Coin};
 use crate::game::Goods;
@@ -11,15 +11,31 @@ pub struct Card {
     pub cost: Coin,
     pub name: String,
     pub base: Option<Base>, // None -> not a base, otherwise which base is it?
+    pub kind: CardKind,
     pub synergizes_with: HashSet<Faction>,
     pub effects: HashSet<(String, String)> // relational structure
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CardStatus {
     pub in_play: bool,
     pub effects_used: HashSet<(String, String)>,
-    pub scrapped: bool
+    pub scrapped: bool,
+    /// effects the card has gained until the end of the turn (ex. by copying another ship)
+    pub turn_effects: HashSet<(String, String)>,
+    /// factions the card has gained until the end of the turn
+    pub turn_factions: HashSet<Faction>,
+    /// whether `turn_factions` replace the card's own factions this turn
+    pub factions_replaced: bool,
+}
+
+/// predicates for picking cards, ex. "a ship that costs 4 or less"
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CardFilter {
+    pub ships_only: bool,
+    pub bases_only: bool,
+    pub max_cost: Option<Coin>,
+    pub faction: Option<Faction>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -28,12 +44,27 @@ pub enum Base {
     Base(Defense)
 }
 
+/// where a card goes once it's acquired
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum CardKind {
+    /// ships and bases, which are shuffled into the player's deck
+    #[default]
+    Deck,
+    /// sits in front of the player, counting as an ally of its factions, until it's scrapped
+    Hero,
+    /// shuffled into the trade deck, and resolved as soon as it's revealed instead of being bought
+    Event,
+}
+
 impl CardStatus {
     pub fn new() -> CardStatus {
         CardStatus {
             in_play: false,
             effects_used: HashSet::new(),
-            scrapped: false
+            scrapped: false,
+            turn_effects: HashSet::new(),
+            turn_factions: HashSet::new(),
+            factions_replaced: false,
         }
     }
     pub fn reveal(&mut self) {
@@ -44,13 +75,35 @@ impl CardStatus {
     }
     pub fn unused_effects(&self, card: &Card) -> HashSet<(String, String)> {
         let mut eff = HashSet::new();
-        for e in card.effects.iter() {
-            if !self.effects_used.contains(e) {
-                eff.insert(e.clone());
+        for e in self.effects(card) {
+            if !self.effects_used.contains(&e) {
+                eff.insert(e);
             }
         }
         eff
     }
+    /// all of the card's effects, including those gained this turn
+    pub fn effects(&self, card: &Card) -> HashSet<(String, String)> {
+        card.effects.union(&self.turn_effects).cloned().collect()
+    }
+    /// all of the card's factions, including those gained this turn
+    pub fn factions(&self, card: &Card) -> HashSet<Faction> {
+        if self.factions_replaced {
+            self.turn_factions.clone()
+        } else {
+            card.synergizes_with.union(&self.turn_factions).cloned().collect()
+        }
+    }
+    /// the card only has this faction until the end of the turn
+    pub fn set_faction_for_turn(&mut self, faction: Faction) {
+        self.turn_factions.clear();
+        self.turn_factions.insert(faction);
+        self.factions_replaced = true;
+    }
+    pub fn gain_for_turn(&mut self, effects: HashSet<(String, String)>, factions: HashSet<Faction>) {
+        self.turn_effects.extend(effects);
+        self.turn_factions.extend(factions);
+    }
     pub fn get_good(&self, goods: &String) -> Option<Goods> {
         parse_goods(goods.as_str())
     }
@@ -61,6 +114,9 @@ impl CardStatus {
     /// protocol for resetting base after a turn is over when it isn't destroyed
     pub fn reset_base(&mut self) {
         self.effects_used.clear();
+        self.turn_effects.clear();
+        self.turn_factions.clear();
+        self.factions_replaced = false;
         // we don't take it "out of play" because it's still revealed
     }
     pub fn use_effect(&mut self, effect: &(String, String)) {
@@ -70,6 +126,11 @@ impl CardStatus {
 }
 
 impl Base {
+    pub fn defense (&self) -> Defense {
+        match self {
+            Base::Outpost(d) | Base::Base(d) => *d
+        }
+    }
     pub fn is_outpost (&self) -> bool {
         match self {
             Base::Outpost(_) => true,
@@ -79,18 +140,55 @@ impl Base {
 }
 
 impl Card {
+    /// not a base, and not a hero
+    pub fn is_ship(&self) -> bool {
+        self.base.is_none() && self.kind == CardKind::Deck
+    }
+    pub fn is_hero(&self) -> bool {
+        self.kind == CardKind::Hero
+    }
+    pub fn is_event(&self) -> bool {
+        self.kind == CardKind::Event
+    }
     fn synergizes_over (&self, faction: &Faction) -> bool {
         self.synergizes_with.contains(faction)
     }
     fn synergizes_with (&self, other: Card) -> HashSet<Faction> {
-        let mut set = HashSet::new();
-        let factions = all_factions();
-        for f in factions {
-            if other.synergizes_over(&f) && self.synergizes_over(&f) {
-                set.insert(f);
-            }
+        self.synergizes_with.iter()
+            .filter(|f| other.synergizes_over(f))
+            .cloned()
+            .collect()
+    }
+}
+
+impl CardFilter {
+    /// matches every card
+    pub fn any() -> CardFilter {
+        CardFilter::default()
+    }
+    pub fn matches(&self, card: &Card) -> bool {
+        (!self.ships_only || card.is_ship())
+            && (!self.bases_only || card.base.is_some())
+            && self.max_cost.is_none_or(|c| card.cost <= c)
+            && self.faction.is_none_or(|f| card.synergizes_with.contains(&f))
+    }
+    /// ex. "ship, cost 4 or less"
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if self.ships_only {
+            parts.push("ship".to_string());
+        } else if self.bases_only {
+            parts.push("base".to_string());
+        } else {
+            parts.push("card".to_string());
+        }
+        if let Some(f) = self.faction {
+            parts.push(format!("{} faction", f));
+        }
+        if let Some(c) = self.max_cost {
+            parts.push(format!("cost {} or less", c));
         }
-        set
+        parts.join(", ")
     }
 }
 
@@ -1,28 +1,105 @@
-use crate::game::components::faction::Faction::*;
-use std::str::FromStr;
-
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
-pub enum Faction {
-    Mech, // The Machine Cult
-    Star, // The Star Empire
-    Blob, // The Blobs
-    Fed, // The Trade Federation
+use std::fmt::{Display, Formatter};
+
+/// A faction, known by its one letter code (ex. 'b' for the Blobs).
+/// Which factions exist, and their names and colours, is up to the card library (see `Factions`)
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Ord, PartialOrd)]
+pub struct Faction(pub char);
+
+impl Display for Faction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-impl FromStr for Faction {
-    type Err = String;
+/// a faction declared in `factions.yaml`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FactionInfo {
+    pub faction: Faction,
+    pub name: String,
+    /// other codes that mean the same faction, ex. 'y' (yellow) for the Star Empire
+    pub aliases: Vec<char>,
+    /// a colour name (ex. "blue") or a hex code (ex. "#ff8800")
+    pub colour: String,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "m" => Ok(Faction::Mech),
-            "s" | "y" => Ok(Faction::Star),
-            "b" => Ok(Faction::Blob),
-            "f" | "t" => Ok(Faction::Fed),
-            _ => Err("Not one of [m, s, y, b, f, t]".to_string())
+/// Every faction cards can have. This is the one place faction codes get parsed,
+/// whether they're in a card's synergy or in an effect like "syn-b"
+#[derive(Clone, Debug, Default)]
+pub struct Factions {
+    factions: Vec<FactionInfo>,
+}
+
+impl Factions {
+    pub fn new() -> Factions {
+        Factions::default()
+    }
+
+    /// the four factions of the base game, for config folders without a factions file
+    pub fn core() -> Factions {
+        let mut factions = Factions::new();
+        for (code, aliases, name, colour) in [
+            ('m', vec![], "Machine Cult", "red"),
+            ('s', vec!['y'], "Star Empire", "yellow"),
+            ('b', vec![], "Blob", "green"),
+            ('t', vec!['f'], "Trade Federation", "blue"),
+        ].iter() {
+            factions.add(FactionInfo {
+                faction: Faction(*code),
+                name: name.to_string(),
+                aliases: aliases.clone(),
+                colour: colour.to_string()
+            }).unwrap();
         }
+        factions
+    }
+
+    /// Err if the faction's code or one of its aliases is already used
+    pub fn add(&mut self, info: FactionInfo) -> Result<(), String> {
+        for code in std::iter::once(&info.faction.0).chain(info.aliases.iter()) {
+            if let Ok(f) = self.parse(&code.to_string()) {
+                return Err(format!("'{}' is the code for both {} and {}", code, self.name(&f), info.name));
+            }
+        }
+        self.factions.push(info);
+        Ok(())
     }
-}
 
-pub fn all_factions() -> Vec<Faction> {
-    vec![Mech, Star, Blob, Fed]
-}
\ No newline at end of file
+    /// the faction with this code (or alias)
+    pub fn parse(&self, code: &str) -> Result<Faction, String> {
+        self.factions.iter()
+            .find(|info| info.faction.0.to_string() == code
+                || info.aliases.iter().any(|a| a.to_string() == code))
+            .map(|info| info.faction)
+            .ok_or(format!("'{}' is not one of [{}]", code, self.codes().join(", ")))
+    }
+
+    /// the same faction, using its main code instead of an alias
+    pub fn canonical(&self, faction: Faction) -> Faction {
+        self.parse(&faction.0.to_string()).unwrap_or(faction)
+    }
+
+    /// in the order they were declared
+    pub fn all(&self) -> Vec<Faction> {
+        self.factions.iter().map(|info| info.faction).collect()
+    }
+
+    pub fn get(&self, faction: &Faction) -> Option<&FactionInfo> {
+        self.factions.iter().find(|info| info.faction == *faction)
+    }
+
+    /// ex. "Blob", or just the code for an unknown faction
+    pub fn name(&self, faction: &Faction) -> String {
+        match self.get(faction) {
+            Some(info) => info.name.clone(),
+            None => faction.to_string()
+        }
+    }
+
+    /// every code and alias, ex. ["m", "s", "y", ...]
+    fn codes(&self) -> Vec<String> {
+        self.factions.iter()
+            .flat_map(|info| std::iter::once(&info.faction.0).chain(info.aliases.iter()))
+            .map(|c| c.to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,10 @@
+/// A secret objective. Once the objective is met, the player can reveal the mission
+/// to complete it and get the reward.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mission {
+    pub name: String,
+    /// an objective predicate, ex. "2 bases in play" (see `effects::get_objective`)
+    pub objective: String,
+    /// an action, ex. "G0.0.3" (see `effects::get_action`)
+    pub reward: String,
+}
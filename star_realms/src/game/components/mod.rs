@@ -1,8 +1,11 @@
 pub mod card;
 pub mod faction;
+pub mod mission;
+pub mod scrap_heap;
 pub mod stack;
 
-pub type Defense = u8;
-pub type Coin = u8;
-pub type Authority = u8;
-pub type Combat = u8;
\ No newline at end of file
+pub type Defense = u32;
+pub type Coin = u32;
+/// signed, since authority can be knocked below zero
+pub type Authority = i32;
+pub type Combat = u32;
\ No newline at end of file
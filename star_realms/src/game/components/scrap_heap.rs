@@ -0,0 +1,70 @@
+use crate::game::components::card::Card;
+use crate::game::Player;
+use std::slice::Iter;
+
+/// where a scrapped card was scrapped from
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScrapSource {
+    /// a card in a player's hand or in play (including bases)
+    Hand,
+    /// a card in the trade row
+    TradeRow,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScrapRecord {
+    pub card: Card,
+    /// the player that scrapped the card
+    pub by: Player,
+    pub from: ScrapSource,
+}
+
+/// The single, game-level pile of scrapped cards.
+/// Explorers never end up here, they go back to the explorer pile instead.
+#[derive(Clone, Debug)]
+pub struct ScrapHeap {
+    records: Vec<ScrapRecord>,
+}
+
+impl ScrapHeap {
+    pub fn new() -> ScrapHeap {
+        ScrapHeap {
+            records: vec![]
+        }
+    }
+
+    pub fn add(&mut self, card: Card, by: Player, from: ScrapSource) {
+        self.records.push(ScrapRecord {
+            card,
+            by,
+            from
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// oldest scraps first
+    pub fn iter(&self) -> Iter<'_, ScrapRecord> {
+        self.records.iter()
+    }
+
+    pub fn scrapped_by(&self, player: Player) -> impl Iterator<Item=&ScrapRecord> {
+        self.records.iter().filter(move |r| r.by == player)
+    }
+
+    pub fn scrapped_from(&self, source: ScrapSource) -> impl Iterator<Item=&ScrapRecord> {
+        self.records.iter().filter(move |r| r.from == source)
+    }
+}
+
+impl Default for ScrapHeap {
+    fn default() -> Self {
+        ScrapHeap::new()
+    }
+}
@@ -67,6 +67,27 @@ impl<T> Stack<T> {
         }
     }
 
+    /// the top `n` elements (or all of them, if there are fewer), starting with the top one
+    pub fn peek_top(&self, n: usize) -> Vec<&T> {
+        self.elements.iter().rev().take(n).collect()
+    }
+
+    /// take the top `n` elements (or all of them, if there are fewer), starting with the top one
+    pub fn draw_top(&mut self, n: usize) -> Vec<T> {
+        let split = self.len().saturating_sub(n);
+        self.elements.split_off(split).into_iter().rev().collect()
+    }
+
+    /// put the elements on top, so that the first one ends up on top
+    pub fn put_on_top(&mut self, elements: Vec<T>) {
+        self.elements.extend(elements.into_iter().rev());
+    }
+
+    /// indexes of the elements matching `f`, starting from the top
+    pub fn positions(&self, f: impl Fn(&T) -> bool) -> Vec<usize> {
+        (0..self.len()).rev().filter(|&i| f(&self.elements[i])).collect()
+    }
+
     /// draw a card from self and place it in other
     pub fn draw_to(&mut self, other: &mut Stack<T>) {
         match self.draw() {
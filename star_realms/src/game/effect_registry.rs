@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use crate::game::effects::{ActionMeta, ActionFunc, ConditionFunc, register_builtins, validate_effects_of, get_objective,
+                          is_compound_condition, get_compound_condition, is_scaled_action, get_scaled_action};
+use crate::game::components::card::Card;
+use crate::game::components::mission::Mission;
+
+/// An action that cards can use, registered under a keyword (see `EffectRegistry`).
+/// Any `Fn(&str, &EffectRegistry) -> Option<(ActionMeta, ActionFunc)>` is one
+pub trait ActionEffect {
+    /// `name` is the whole action, ex. "draw 2" for the keyword "draw".
+    /// None if it's not a valid action.
+    /// `effects` is there for actions made of other actions, ex. "each player draw"
+    fn get_action(&self, name: &str, effects: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)>;
+}
+
+/// A condition that cards can use, registered under a keyword (see `EffectRegistry`).
+/// Any `Fn(&str, &EffectRegistry) -> Option<ConditionFunc>` is one
+pub trait ConditionEffect {
+    /// `name` is the whole condition, ex. "syn-b" for the keyword "syn".
+    /// None if it's not a valid condition
+    fn get_condition(&self, name: &str, effects: &EffectRegistry) -> Option<ConditionFunc>;
+}
+
+impl<F> ActionEffect for F where F: Fn(&str, &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    fn get_action(&self, name: &str, effects: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+        self(name, effects)
+    }
+}
+
+impl<F> ConditionEffect for F where F: Fn(&str, &EffectRegistry) -> Option<ConditionFunc> {
+    fn get_condition(&self, name: &str, effects: &EffectRegistry) -> Option<ConditionFunc> {
+        self(name, effects)
+    }
+}
+
+/// Every action and condition the cards in a library can use, by keyword.
+/// An effect belongs to the longest keyword it starts with, as long as the keyword
+/// isn't followed by a letter, so "draw 2" goes to "draw" and "G0.0.1" to "G",
+/// but "drawing" doesn't go anywhere.
+///
+/// The built-in effects are registered like any others. A homebrew crate can add its own
+/// through `CardLibrary::get_effects_mut`, before the library goes into a game.
+pub struct EffectRegistry {
+    actions: HashMap<String, Box<dyn ActionEffect>>,
+    conditions: HashMap<String, Box<dyn ConditionEffect>>,
+}
+
+impl EffectRegistry {
+    /// no effects at all
+    pub fn empty() -> EffectRegistry {
+        EffectRegistry {
+            actions: HashMap::new(),
+            conditions: HashMap::new(),
+        }
+    }
+
+    /// the built-in effects
+    pub fn new() -> EffectRegistry {
+        let mut effects = EffectRegistry::empty();
+        register_builtins(&mut effects);
+        effects
+    }
+
+    /// Err if the keyword is already taken
+    pub fn register_action(&mut self, keyword: &str, action: impl ActionEffect + 'static) -> Result<(), String> {
+        if self.actions.contains_key(keyword) {
+            return Err(format!("There is already an action for '{}'", keyword));
+        }
+        self.actions.insert(keyword.to_string(), Box::new(action));
+        Ok(())
+    }
+
+    /// Err if the keyword is already taken
+    pub fn register_condition(&mut self, keyword: &str, condition: impl ConditionEffect + 'static) -> Result<(), String> {
+        if self.conditions.contains_key(keyword) {
+            return Err(format!("There is already a condition for '{}'", keyword));
+        }
+        self.conditions.insert(keyword.to_string(), Box::new(condition));
+        Ok(())
+    }
+
+    pub fn get_action(&self, name: &str) -> Option<(ActionMeta, ActionFunc)> {
+        // ex. "draw for each b card played this turn", made out of another action
+        if is_scaled_action(name) {
+            return get_scaled_action(name, self);
+        }
+        find(&self.actions, name)?.get_action(name, self)
+    }
+
+    pub fn get_condition(&self, name: &str) -> Option<ConditionFunc> {
+        // ex. "syn-b and scrap", made out of other conditions
+        if is_compound_condition(name) {
+            return get_compound_condition(name, self);
+        }
+        find(&self.conditions, name)?.get_condition(name, self)
+    }
+
+    /// None -> valid
+    /// String -> invalid, with reason
+    pub fn validate_card_effects(&self, card: &Card) -> Option<String> {
+        validate_effects_of(self, card)
+    }
+
+    /// None -> valid
+    /// String -> invalid, with reason.
+    /// (objectives aren't effects, see `effects::get_objective`)
+    pub fn validate_mission(&self, mission: &Mission) -> Option<String> {
+        if get_objective(&mission.objective).is_none() {
+            return Some(format!("Invalid objective: {}", mission.objective));
+        }
+        match self.get_action(&mission.reward) {
+            Some(_) => None,
+            None => Some(format!("Invalid action: {}", mission.reward))
+        }
+    }
+}
+
+impl Default for EffectRegistry {
+    fn default() -> Self {
+        EffectRegistry::new()
+    }
+}
+
+/// the effect with the longest keyword that `name` starts with
+fn find<'a, T: ?Sized>(effects: &'a HashMap<String, Box<T>>, name: &str) -> Option<&'a T> {
+    effects.iter()
+        .filter(|(keyword, _)| name.strip_prefix(keyword.as_str())
+            .is_some_and(|rest| !rest.starts_with(char::is_alphabetic)))
+        .max_by_key(|(keyword, _)| keyword.len())
+        .map(|(_, effect)| effect.as_ref())
+}
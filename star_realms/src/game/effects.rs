@@ -1,22 +1,35 @@
 use std::ops::{AddAssign, Add};
-use crate::game::{Goods, GameState, RelativePlayer, HandId};
+use crate::game::{Goods, GameState, RelativePlayer, HandId, AcquireTo, TRADE_ROW_SIZE, Player, Zone, Counter,
+                  STARTING_AUTHORITY};
 use crate::game::util::Failure::{Succeed, Fail};
 use crate::game::util::Failure;
 use crate::game::RelativePlayer::Opponent;
-use crate::game::components::card::{Base, Card};
+use crate::game::components::card::{Base, Card, CardFilter, CardKind};
 use std::collections::HashSet;
 use crate::parse::parse_goods;
 use std::fmt::{Display, Formatter};
 use ansi_term::Color;
 use crate::game::components::faction::Faction;
-use std::rc::Rc;
+use crate::game::components::scrap_heap::ScrapSource;
+use crate::game::components::mission::Mission;
+use crate::game::components::Authority;
+use crate::game::script::{get_script_action, get_script_condition, SCRIPT_PREFIX};
+use crate::game::effect_registry::EffectRegistry;
+
+/// a regex that's compiled the first time it's used, instead of every time
+macro_rules! regex {
+    ($pattern:expr) => {{
+        static PATTERN: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+        PATTERN.get_or_init(|| regex::Regex::new($pattern).unwrap())
+    }};
+}
 
 /// Effects!
 
 pub type ConfigError = String;
 
-/// FnMut(game, config_value) -> Failure<String>
-pub type ActionFunc = Box<dyn FnMut(&mut GameState, u32) -> Failure<ConfigError>>;
+/// FnMut(game, hand_id /* of the card using the action */, config_value) -> Failure<String>
+pub type ActionFunc = Box<dyn FnMut(&mut GameState, &HandId, u32) -> Failure<ConfigError>>;
 
 pub struct ActionMeta {
     /// description of the action, (probably?) user-friendly
@@ -63,61 +76,187 @@ pub enum ActionConfigMethod {
     /// num: u32, by: u32
     /// num = number of trade row cards to pick
     /// by = player that is picking them
-    PickTradeRowCards(u32, RelativePlayer)
+    PickTradeRowCards(u32, RelativePlayer),
+
+    /// by: Player, filter: CardFilter
+    /// by = player that is picking the card
+    /// filter = only cards matching this can be picked
+    /// config should be the index of a matching card in the trade row
+    /// (if nothing matches, any config is accepted and the action does nothing)
+    PickTradeRowCard(RelativePlayer, CardFilter),
+
+    /// by: Player, owner: Player, respect_outposts: bool
+    /// by = player that is picking the base
+    /// owner = player whose base is picked
+    /// respect_outposts = if there are outposts, one of them has to be picked
+    /// config should be the hand id of one of `owner`'s bases in play
+    /// (if there are none, any config is accepted and the action does nothing)
+    PickBase(RelativePlayer, RelativePlayer, bool),
+
+    /// by: Player
+    /// by = player that is picking one of their own cards with a trash effect
+    /// config should be one of `PlayerArea::scrappable_card_ids`
+    PickScrappableCard(RelativePlayer),
+
+    /// by: Player
+    /// by = player that is picking a faction
+    /// config should be an index into the card library's `Factions::all`
+    PickFaction(RelativePlayer),
+
+    /// num: u32, by: Player
+    /// num = number of cards from the top of `by`'s deck (see `PlayerArea::top_of_deck`)
+    /// by = player that is putting them in order
+    /// config should be an `encode_arrangement` of the cards to put back, top first.
+    /// the rest are discarded
+    ArrangeTopOfDeck(u32, RelativePlayer),
+
+    /// by: Player, filter: CardFilter
+    /// by = player that is searching their deck
+    /// filter = only cards matching this can be picked
+    /// config should be one of `GameState::deck_choices`
+    /// (if nothing matches, any config is accepted and the action does nothing)
+    PickDeckCard(RelativePlayer, CardFilter),
+}
+
+/// the most cards `ArrangeTopOfDeck` can put in order, one for every 4 bits of the config
+pub const MAX_ARRANGED: u32 = 8;
+
+/// The config for `ArrangeTopOfDeck`. `order` is indexes into `PlayerArea::top_of_deck`,
+/// ex. [2, 0] puts the third card on top of the first one, and discards the second
+pub fn encode_arrangement(order: &[usize]) -> u32 {
+    order.iter()
+        .take(MAX_ARRANGED as usize)
+        .enumerate()
+        .fold(0, |config, (position, &i)| config | (((i as u32 + 1) & 0xf) << (4 * position)))
+}
+
+pub fn decode_arrangement(config: u32) -> Vec<usize> {
+    (0..MAX_ARRANGED)
+        .map(|position| (config >> (4 * position)) & 0xf)
+        .take_while(|&i| i != 0)
+        .map(|i| i as usize - 1)
+        .collect()
 }
 
 /// FnMut(game, hand_id /* of card */) -> bool
 pub type ConditionFunc = Box<dyn FnMut(&GameState, &u32) -> bool>;
 
-pub fn validate_condition(name: &String) -> Option<String> {
-    match get_condition(name.clone()) {
+/// Fn(game, player /* whose objective it is */) -> bool
+pub type ObjectiveFunc = Box<dyn Fn(&GameState, Player) -> bool>;
+
+thread_local! {
+    /// the built-in effects, for the free functions below
+    static BUILTINS: EffectRegistry = EffectRegistry::new();
+}
+
+fn with_builtins<R>(f: impl FnOnce(&EffectRegistry) -> R) -> R {
+    BUILTINS.with(f)
+}
+
+pub fn validate_condition(name: &str) -> Option<String> {
+    with_builtins(|effects| validate_condition_in(effects, name))
+}
+
+pub fn validate_action(name: &str) -> Option<String> {
+    with_builtins(|effects| validate_action_in(effects, name))
+}
+
+fn validate_condition_in(effects: &EffectRegistry, name: &str) -> Option<String> {
+    match effects.get_condition(name) {
         Some(_) => None,
         None => Some(format!("Invalid condition: {}", name))
     }
 }
 
-pub fn validate_action(name: &String) -> Option<String> {
-    match get_action(name) {
+fn validate_action_in(effects: &EffectRegistry, name: &str) -> Option<String> {
+    match effects.get_action(name) {
         Some(_) => None,
         None => Some(format!("Invalid action: {}", name))
     }
 }
 
 pub fn validate_effect((cond, act): (&String, &String)) -> Option<String> {
-    match validate_condition(cond) {
-        None => match validate_action(act) {
-            None => None,
-            x => x
-        },
-        x => x,
-    }
+    with_builtins(|effects| validate_condition_in(effects, cond).or_else(|| validate_action_in(effects, act)))
 }
 
 /// None -> valid
-/// String -> invalid, with reason
+/// String -> invalid, with reason.
+/// Only the built-in effects are known here, see `EffectRegistry::validate_card_effects`
 pub fn validate_card_effects(card: &Card) -> Option<String> {
+    with_builtins(|effects| effects.validate_card_effects(card))
+}
+
+pub(crate) fn validate_effects_of(effects: &EffectRegistry, card: &Card) -> Option<String> {
+    let get_action = |name: &str| effects.get_action(name);
     for (l, r) in card.effects.iter() {
-        if let Some(e) = validate_effect((l, r)) {
+        if let Some(e) = validate_condition_in(effects, l).or_else(|| validate_action_in(effects, r)) {
             return Some(e)
         }
+        let acquired = get_trigger(l) == Some(Trigger::Acquired);
+        if get_placement(r).is_some() && !acquired {
+            return Some(format!("'{}' only works when the card is acquired", r));
+        }
+        // acquisitions can happen in the middle of other effects, so there's nobody to make choices
+        if acquired && get_action(r).is_some_and(|(meta, _)| !meta.no_config()) {
+            return Some(format!("Effects when acquired cannot need choices: {}", r));
+        }
+        // events resolve by themselves, so nobody can make choices for them
+        if card.is_event() {
+            if !is_event_cond(l) {
+                return Some(format!("Events can only have '{}' effects, not '{}'", "event", l));
+            }
+            if get_action(r).is_some_and(|(meta, _)| !meta.no_config()) {
+                return Some(format!("Event effects cannot need choices: {}", r));
+            }
+        }
     }
     None
 }
 
+/// None -> valid
+/// String -> invalid, with reason
+pub fn validate_mission(mission: &Mission) -> Option<String> {
+    with_builtins(|effects| effects.validate_mission(mission))
+}
+
 pub fn assert_validate_card_effects(card: &Card) {
     if let Some(e) = validate_card_effects(&card) {
         panic!("{} was not a valid card because '{}': {:?}", card.name, e, card);
     }
 }
 
-/// determines if an condition key string signals the "scrap" condition
-/// (appears as a trash can on the actual cards)
+/// determines if an condition key string needs the "scrap" condition, ex. "scrap" or "syn-b and scrap"
+/// (appears as a trash can on the actual cards). "not scrap" and "syn-b or scrap" don't need it
 pub fn is_trash_cond(cond: &String) -> bool {
-    if let "trash" | "scrap" = cond.as_str() {
-        true
-    } else {
-        false
+    requires_scrap(&condition_tokens(cond))
+}
+
+/// whether scrap is one of the top level "and"s, not under a "not" or next to an "or"
+fn requires_scrap(tokens: &[String]) -> bool {
+    let mut conjuncts = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, token) in tokens.iter().enumerate() {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            "or" if depth == 0 => return false,
+            "and" if depth == 0 => {
+                conjuncts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
     }
+    conjuncts.push(&tokens[start..]);
+    conjuncts.iter().any(|conjunct| match conjunct {
+        [token] => matches!(token.as_str(), "trash" | "scrap"),
+        [open, inner @ .., close] if open == "(" && close == ")" => requires_scrap(inner),
+        _ => false
+    })
+}
+/// the effects of event cards, which resolve when they're revealed in the trade row
+pub fn is_event_cond(cond: &str) -> bool {
+    cond == "event"
 }
 pub fn is_free_cond(cond: &String) -> bool {
     match cond.as_str() {
@@ -125,36 +264,422 @@ pub fn is_free_cond(cond: &String) -> bool {
         _ => false
     }
 }
+/// effects that happen automatically, rather than being chosen by the player
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Trigger {
+    StartOfTurn,
+    EndOfTurn,
+    /// when the card is acquired, before it goes anywhere
+    Acquired,
+}
+
+/// determines if a condition key string is a trigger, ex. "start" for "at the start of your turn"
+pub fn get_trigger(cond: &str) -> Option<Trigger> {
+    match cond {
+        "start" | "start-of-turn" => Some(Trigger::StartOfTurn),
+        "end" | "end-of-turn" => Some(Trigger::EndOfTurn),
+        "acquired" => Some(Trigger::Acquired),
+        // ex. "acquired-syn-m", when acquired while a Machine Cult card is in play
+        _ if cond.strip_prefix("acquired-syn-").is_some_and(|f| f.chars().count() == 1) =>
+            Some(Trigger::Acquired),
+        _ => None
+    }
+}
+
+/// whether an `Acquired` trigger happens for the current player
+pub fn acquired_condition_met(game: &GameState, cond: &str) -> bool {
+    match cond.strip_prefix("acquired-syn-").map(|f| game.card_library.get_factions().parse(f)) {
+        // the card being acquired isn't in play, so any card in play counts as an ally
+        Some(Ok(faction)) => game.get_current_player().has_ally(&HandId::MAX, &faction),
+        Some(Err(_)) => false,
+        None => cond == "acquired"
+    }
+}
+
+/// "to hand" and "to top" change where a card goes when it's acquired.
+/// they only make sense for `Acquired` triggers
+pub fn get_placement(action: &str) -> Option<AcquireTo> {
+    match action {
+        "to hand" => Some(AcquireTo::Hand),
+        "to top" => Some(AcquireTo::TopOfDeck),
+        _ => None
+    }
+}
+
+/// a built-in condition (see `EffectRegistry` for the card library's)
 pub fn get_condition(name: String) -> Option<ConditionFunc> {
-    match name.as_str() {
-        _ if is_free_cond(&name) => Some(Box::new(|_, _| true)),
-        _ if is_trash_cond(&name) => Some(Box::new(
-            |game, id| {
-                game.get_current_player().hand_id.get(id)
-                    .expect("trash condition: bad id supplied")
-                    .1.scrapped
+    with_builtins(|effects| effects.get_condition(&name))
+}
+
+/// a built-in action (see `EffectRegistry` for the card library's)
+pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
+    with_builtins(|effects| effects.get_action(name))
+}
+
+type ConditionParser = fn(&str, &EffectRegistry) -> Option<ConditionFunc>;
+type ActionParser = fn(&str, &EffectRegistry) -> Option<(ActionMeta, ActionFunc)>;
+
+/// register every built-in effect under its keyword(s)
+pub(crate) fn register_builtins(effects: &mut EffectRegistry) {
+    let conditions: Vec<(&str, ConditionParser)> = vec![
+        ("free", free_condition),
+        ("any", free_condition),
+        ("trash", trash_condition),
+        ("scrap", trash_condition),
+        ("syn", synergy_condition),
+        ("start", trigger_condition),
+        ("start-of-turn", trigger_condition),
+        ("end", trigger_condition),
+        ("end-of-turn", trigger_condition),
+        ("acquired", trigger_condition),
+        ("event", trigger_condition),
+        ("at least", count_condition),
+        ("at most", count_condition),
+        ("exactly", count_condition),
+        ("script", |name, _| get_script_condition(name)),
+    ];
+    for (keyword, condition) in conditions {
+        effects.register_condition(keyword, condition).unwrap();
+    }
+    let actions: Vec<(&str, ActionParser)> = vec![
+        ("each player", each_player_action),
+        ("G", goods_action),
+        ("draw", draw_action),
+        ("scrap trade row for", scrap_trade_row_for_action),
+        ("scrap trade row", scrap_trade_row_action),
+        ("next acquire", next_acquire_action),
+        ("to", placement_action),
+        ("add faction", faction_action),
+        ("set faction", faction_action),
+        ("acquire", acquire_action),
+        ("look at top", look_at_top_action),
+        ("search deck for", search_deck_action),
+        ("destroy target base", destroy_base_action),
+        ("test", misc_action),
+        ("discard", misc_action),
+        ("copy ship", misc_action),
+        ("script", |name, _| get_script_action(name)),
+    ];
+    for (keyword, action) in actions {
+        effects.register_action(keyword, action).unwrap();
+    }
+}
+
+fn free_condition(name: &str, _: &EffectRegistry) -> Option<ConditionFunc> {
+    if !is_free_cond(&name.to_string()) {
+        return None;
+    }
+    Some(Box::new(|_, _| true))
+}
+
+/// the engine resolves these itself, they can never be chosen
+fn trigger_condition(name: &str, _: &EffectRegistry) -> Option<ConditionFunc> {
+    if get_trigger(name).is_none() && !is_event_cond(name) {
+        return None;
+    }
+    Some(Box::new(|_, _| false))
+}
+
+fn trash_condition(name: &str, _: &EffectRegistry) -> Option<ConditionFunc> {
+    if !matches!(name, "trash" | "scrap") {
+        return None;
+    }
+    Some(Box::new(
+        |game, id| {
+            game.get_current_player().get_card(id)
+                .expect("trash condition: bad id supplied")
+                .1.scrapped
+        }
+    ))
+}
+
+/// example: "syn t" for synergy with Trade Federation
+/// (another card of that faction has to be in play)
+fn synergy_condition(name: &str, _: &EffectRegistry) -> Option<ConditionFunc> {
+    let code = name.chars().last()?.to_string();
+    Some(Box::new(
+        // the library decides which factions exist, an unknown one is never an ally
+        move |game, id| match game.card_library.get_factions().parse(&code) {
+            Ok(p) => game.get_current_player().has_ally(id, &p),
+            Err(_) => false
+        }
+    ))
+}
+
+/// Counts the current player's cards, ex. "at least 2 bases in play",
+/// "at most 1 b card played this turn" or "exactly 0 cards in hand".
+/// The zones are "in play", "in hand", "played this turn", "in discard" and "in deck",
+/// and the card using the condition counts too, so "another Blob card played this turn"
+/// is "at least 2 b cards played this turn"
+fn count_condition(name: &str, _: &EffectRegistry) -> Option<ConditionFunc> {
+    let pattern = regex!(
+        r"^(at least|at most|exactly) (\d+) (?:(\w) )?(cards?|ships?|bases?|outposts?) (in play|in hand|played this turn|in discard|in deck)$"
+    );
+    let caps = pattern.captures(name)?;
+    let n: usize = caps[2].parse().ok()?;
+    let compare: fn(usize, usize) -> bool = match &caps[1] {
+        "at least" => |count, n| count >= n,
+        "at most" => |count, n| count <= n,
+        _ => |count, n| count == n
+    };
+    let faction = caps.get(3).map(|f| f.as_str().to_string());
+    let kind = caps[4].trim_end_matches('s').to_string();
+    let zone = match &caps[5] {
+        "in play" => Zone::InPlay,
+        "in hand" => Zone::InHand,
+        "played this turn" => Zone::PlayedThisTurn,
+        "in discard" => Zone::Discard,
+        _ => Zone::Deck
+    };
+    Some(Box::new(move |game, _| {
+        let faction = match &faction {
+            // the library decides which factions exist, there are none of an unknown one
+            Some(code) => match game.card_library.get_factions().parse(code) {
+                Ok(f) => Some(f),
+                Err(_) => return compare(0, n)
+            },
+            None => None
+        };
+        let count = game.get_current_player().cards_in_zone(zone).iter()
+            .filter(|(card, factions)| match kind.as_str() {
+                "ship" => card.is_ship(),
+                "base" => card.base.is_some(),
+                "outpost" => card.base.as_ref().is_some_and(|b| b.is_outpost()),
+                _ => true
+            } && faction.is_none_or(|f| factions.contains(&f)))
+            .count();
+        compare(count, n)
+    }))
+}
+
+/// the words of a condition, with brackets split off. scripts are one word
+fn condition_tokens(cond: &str) -> Vec<String> {
+    if cond.starts_with(SCRIPT_PREFIX) {
+        return vec![cond.to_string()];
+    }
+    cond.replace('(', " ( ").replace(')', " ) ")
+        .split_whitespace()
+        .map(|token| token.to_string())
+        .collect()
+}
+
+/// whether a condition is made of other conditions, see `get_compound_condition`
+pub fn is_compound_condition(cond: &str) -> bool {
+    condition_tokens(cond).iter().any(|token| matches!(token.as_str(), "and" | "or" | "not" | "(" | ")"))
+}
+
+/// Conditions combined with "and", "or", "not" and brackets, ex. "syn-b and scrap"
+/// or "not (at least 2 bases in play or syn-s)". "not" comes first, then "and", then "or".
+/// The conditions can be any in the registry, except for triggers and events,
+/// which the engine has to find by themselves
+pub(crate) fn get_compound_condition(name: &str, effects: &EffectRegistry) -> Option<ConditionFunc> {
+    let tokens = condition_tokens(name);
+    let mut parser = CompoundParser { tokens: &tokens, pos: 0, effects };
+    let mut tree = parser.any()?;
+    if parser.pos != tokens.len() {
+        return None;
+    }
+    Some(Box::new(move |game, id| tree.eval(game, id)))
+}
+
+enum ConditionTree {
+    Leaf(ConditionFunc),
+    Not(Box<ConditionTree>),
+    All(Vec<ConditionTree>),
+    Any(Vec<ConditionTree>),
+}
+
+impl ConditionTree {
+    fn eval(&mut self, game: &GameState, id: &HandId) -> bool {
+        match self {
+            ConditionTree::Leaf(cond) => cond(game, id),
+            ConditionTree::Not(tree) => !tree.eval(game, id),
+            ConditionTree::All(trees) => trees.iter_mut().all(|tree| tree.eval(game, id)),
+            ConditionTree::Any(trees) => trees.iter_mut().any(|tree| tree.eval(game, id)),
+        }
+    }
+}
+
+struct CompoundParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    effects: &'a EffectRegistry,
+}
+
+impl<'a> CompoundParser<'a> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.as_str())
+    }
+    /// a or b or ...
+    fn any(&mut self) -> Option<ConditionTree> {
+        let mut trees = vec![self.all()?];
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            trees.push(self.all()?);
+        }
+        Some(if trees.len() == 1 { trees.pop()? } else { ConditionTree::Any(trees) })
+    }
+    /// a and b and ...
+    fn all(&mut self) -> Option<ConditionTree> {
+        let mut trees = vec![self.unary()?];
+        while self.peek() == Some("and") {
+            self.pos += 1;
+            trees.push(self.unary()?);
+        }
+        Some(if trees.len() == 1 { trees.pop()? } else { ConditionTree::All(trees) })
+    }
+    /// not a, (a), or a condition from the registry
+    fn unary(&mut self) -> Option<ConditionTree> {
+        match self.peek()? {
+            "not" => {
+                self.pos += 1;
+                Some(ConditionTree::Not(Box::new(self.unary()?)))
             }
-        )),
-        // example: "syn t" for synergy with Trade Federation
-        _ if name.starts_with("syn") => Some(Box::new({
-                let n = name.clone();
-                move |game, id| match &(n.as_str()[n.len()-1..].parse()) {
-                    Ok(p) => game.get_current_player()
-                        .get_card_in_hand(id)
-                        .expect("synergy condition: bad id supplied")
-                        .0.synergizes_with.contains(p),
-                    Err(e) => panic!("'{}' is not a valid condition! {}", &n, e)
+            "(" => {
+                self.pos += 1;
+                let tree = self.any()?;
+                if self.peek() != Some(")") {
+                    return None;
                 }
-            })
-        ),
-        _ => None
+                self.pos += 1;
+                Some(tree)
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|token| !matches!(token, "and" | "or" | "not" | "(" | ")")) {
+                    self.pos += 1;
+                }
+                let name = self.tokens[start..self.pos].join(" ");
+                if name.is_empty() || get_trigger(&name).is_some() || is_event_cond(&name) {
+                    return None;
+                }
+                self.effects.get_condition(&name).map(ConditionTree::Leaf)
+            }
+        }
     }
 }
 
-pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
+/// Mission objectives, ex.
+/// "2 bases in play", "1 outposts in play", "3 b cards in play", "60 authority", "4 cards scrapped"
+pub fn get_objective(name: &str) -> Option<ObjectiveFunc> {
+    let pattern = regex!(r"^(\d+) (bases|outposts|\w cards) in play$");
+    if let Some(caps) = pattern.captures(name) {
+        let n: usize = caps[1].parse().ok()?;
+        let (bases_only, outposts_only, faction) = match &caps[2] {
+            "bases" => (true, false, None),
+            "outposts" => (true, true, None),
+            cards => (false, false, Some(cards[..1].to_string()))
+        };
+        return Some(Box::new(move |game, player| {
+            let faction = match &faction {
+                Some(code) => match game.card_library.get_factions().parse(code) {
+                    Ok(f) => Some(f),
+                    Err(_) => return false
+                },
+                None => None
+            };
+            game.get_player(player).effect_cards()
+                .filter(|(_, (card, status))| status.in_play
+                    && (!bases_only || card.base.is_some())
+                    && (!outposts_only || card.base.as_ref().is_some_and(|b| b.is_outpost()))
+                    && faction.is_none_or(|f| status.factions(card).contains(&f)))
+                .count() >= n
+        }));
+    }
+    let pattern = regex!(r"^(\d+) authority$");
+    if let Some(caps) = pattern.captures(name) {
+        let n: Authority = caps[1].parse().ok()?;
+        // only for players who start with the usual authority,
+        // the raid boss and teams would have it from the start
+        return Some(Box::new(move |game, player| {
+            game.starting_authority(player) == STARTING_AUTHORITY && game.authority_of(player) >= n
+        }));
+    }
+    let pattern = regex!(r"^(\d+) cards scrapped$");
+    if let Some(caps) = pattern.captures(name) {
+        let n: usize = caps[1].parse().ok()?;
+        return Some(Box::new(move |game, player| game.get_scrap_heap().scrapped_by(player).count() >= n));
+    }
+    None
+}
+
+fn each_player_action(name: &str, effects: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    // example: "each player draw"
+    // every player does the action, starting with the current player
+    if let Some(inner) = name.strip_prefix("each player ") {
+        let (meta, mut action) = effects.get_action(inner)?;
+        if !meta.no_config() {
+            // one player can't make choices for everyone
+            return None;
+        }
+        return Some(
+            (
+                ActionMeta {
+                    description: format!("Each player: {}", meta.description),
+                    config: None
+                },
+                Box::new(move |game: &mut GameState, id: &HandId, config: u32| {
+                    game.for_each_player(|game| action(game, id, config))
+                })
+            )
+        );
+    }
+    None
+}
+
+/// whether an action scales with a `Counter`, see `get_scaled_action`
+pub fn is_scaled_action(name: &str) -> bool {
+    !name.starts_with(SCRIPT_PREFIX) && name.contains(" for each ")
+}
+
+/// An action done once for each of something, ex. "draw for each b card played this turn",
+/// "G0.0.1 for each base in play" or "G1.0.0 for each ship in play".
+/// The action can be anything in the registry that doesn't need to be configured
+pub(crate) fn get_scaled_action(name: &str, effects: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    let pattern = regex!(r"^(.+) for each ((\w) card played this turn|base in play|ship in play)$");
+    let caps = pattern.captures(name)?;
+    let (meta, mut action) = effects.get_action(&caps[1])?;
+    if !meta.no_config() {
+        // every time would need its own choice
+        return None;
+    }
+    let faction = caps.get(3).map(|f| f.as_str().to_string());
+    let counter = match &caps[2] {
+        "base in play" => Some(Counter::BasesInPlay),
+        "ship in play" => Some(Counter::ShipsInPlay),
+        // the faction depends on the library
+        _ => None
+    };
+    Some(
+        (
+            ActionMeta {
+                description: format!("{}, for each {}", meta.description, &caps[2]),
+                config: None
+            },
+            Box::new(move |game: &mut GameState, id: &HandId, config: u32| {
+                let counter = match counter {
+                    Some(counter) => counter,
+                    None => match game.card_library.get_factions().parse(faction.as_deref().unwrap_or_default()) {
+                        Ok(f) => Counter::FactionPlayed(f),
+                        Err(e) => return Fail(e)
+                    }
+                };
+                // counted before the action, which might change it
+                for _ in 0..game.get_current_player().counter(counter) {
+                    if let Fail(e) = action(game, id, config) {
+                        return Fail(e);
+                    }
+                }
+                Succeed
+            })
+        )
+    )
+}
+
+fn goods_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
     // signal to be a good
     if name.starts_with("G") {
-        if let Some(goods) = parse_goods(name.as_str()) {
+        if let Some(goods) = parse_goods(name) {
             let action = get_good_action(goods);
             return Some(
                 (
@@ -167,8 +692,12 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
             )
         }
     }
-    let pattern = regex::Regex::new(r"draw(\s\d)?").unwrap();
-    if pattern.is_match(name.as_str()) {
+    None
+}
+
+fn draw_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    let pattern = regex!(r"draw(\s\d)?");
+    if pattern.is_match(name) {
         if let Some(captures) = pattern.captures(name) {
             if let Some(n) = captures.get(1) {
                 if let Ok(n) = n.as_str().parse::<u32>() {
@@ -178,7 +707,7 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
                                 description: format!("Draw {} cards from your deck", &n),
                                 config: None
                             },
-                            Box::new(move |game, _| {
+                            Box::new(move |game, _, _| {
                                 for _ in 0..n {
                                     game.get_current_player_mut().draw_into_hand();
                                 }
@@ -194,7 +723,7 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
                             description: "Draw a card from your deck".to_string(),
                             config: None
                         },
-                        Box::new(|game, _| {
+                        Box::new(|game, _, _| {
                             game.get_current_player_mut().draw_into_hand();
                             Succeed
                         })
@@ -203,8 +732,88 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
             }
         }
     }
-    let pattern = regex::Regex::new(r"scrap trade row( \d)?").unwrap();
-    if pattern.is_match(name.as_str()) {
+    None
+}
+
+fn scrap_trade_row_for_action(name: &str, effects: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    // example: "scrap trade row for G0.0.2", the reward is only given if a card is scrapped
+    if let Some(reward) = name.strip_prefix("scrap trade row for ") {
+        let (reward_meta, mut reward) = effects.get_action(reward)?;
+        if !reward_meta.no_config() {
+            return None;
+        }
+        return Some(
+            (
+                ActionMeta {
+                    description: format!("Scrap a card in the trade row, then: {}", reward_meta.description),
+                    config: Some(Config {
+                        describe: Box::new(|_| "Pick a card from the trade row".to_string()),
+                        config_method: ActionConfigMethod::PickTradeRowCards(1, RelativePlayer::Current)
+                    })
+                },
+                Box::new(move |game, id, cfg| {
+                    let cards = game.unpack_multi_trade_row_card_selection(&cfg);
+                    let cards = game.remove_cards_from_trade_row(cards);
+                    if cards.is_empty() {
+                        return Succeed;
+                    }
+                    let by = game.current_player;
+                    for c in cards {
+                        game.scrap_card(c, by, ScrapSource::TradeRow);
+                    }
+                    game.fill_trade_row(TRADE_ROW_SIZE);
+                    reward(game, id, 0)
+                })
+            )
+        );
+    }
+    None
+}
+
+fn next_acquire_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    // example: "next acquire to top", for the next card acquired this turn
+    if let Some(to) = name.strip_prefix("next acquire ").and_then(get_placement) {
+        return Some(
+            (
+                ActionMeta {
+                    description: format!("The next card you acquire this turn goes {}", match to {
+                        AcquireTo::Hand => "into your hand",
+                        _ => "on top of your deck"
+                    }),
+                    config: None
+                },
+                Box::new(move |game, _, _| {
+                    game.get_current_player_mut().set_next_acquire_to(to);
+                    Succeed
+                })
+            )
+        );
+    }
+    None
+}
+
+fn placement_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    if let Some(to) = get_placement(name) {
+        return Some(
+            (
+                ActionMeta {
+                    description: format!("Put this card {} when it's acquired", match to {
+                        AcquireTo::Hand => "into your hand",
+                        _ => "on top of your deck"
+                    }),
+                    config: None
+                },
+                // `GameState::acquire_from_trade_row` takes care of these
+                Box::new(|_, _, _| Fail("This only works when the card is acquired".to_string()))
+            )
+        );
+    }
+    None
+}
+
+fn scrap_trade_row_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    let pattern = regex!(r"scrap trade row( \d)?");
+    if pattern.is_match(name) {
         if let Some(captures) = pattern.captures(name) {
             match captures.get(1) {
                 Some(n) => {
@@ -228,14 +837,14 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
                                     )
                                 }),
                             },
-                            Box::new(|game, cfg| {
+                            Box::new(|game, _, cfg| {
                                 let cards = game.unpack_multi_trade_row_card_selection(&cfg);
                                 let cards = game.remove_cards_from_trade_row(cards);
+                                let by = game.current_player;
                                 for c in cards {
-                                    game.scrapped.add(c);
+                                    game.scrap_card(c, by, ScrapSource::TradeRow);
                                 }
-                                // todo: AAAA MAGIC NUMBERS
-                                game.fill_trade_row(5);
+                                game.fill_trade_row(TRADE_ROW_SIZE);
                                 Succeed
                             })
                             )
@@ -251,15 +860,15 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
                                     config_method: ActionConfigMethod::PickTradeRowCards(1, RelativePlayer::Current)
                                 })
                             },
-                            Box::new(|game, cfg| {
+                            Box::new(|game, _, cfg| {
                                 // same implementation even though it's just one card, idc
                                 let cards = game.unpack_multi_trade_row_card_selection(&cfg);
                                 let cards = game.remove_cards_from_trade_row(cards);
+                                let by = game.current_player;
                                 for c in cards {
-                                    game.scrapped.add(c);
+                                    game.scrap_card(c, by, ScrapSource::TradeRow);
                                 }
-                                // todo: AAAA MAGIC NUMBERS
-                                game.fill_trade_row(5);
+                                game.fill_trade_row(TRADE_ROW_SIZE);
                                 Succeed
                             })
                         )
@@ -268,18 +877,189 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
             }
         }
     }
-    match name.as_str() {
+    None
+}
+
+fn faction_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    // "add faction" gives the card another faction for the turn, "set faction" replaces them.
+    // the faction can be fixed, ex. "add faction b", otherwise the player chooses
+    let pattern = regex!(r"^(add|set) faction( \w)?$");
+    if let Some(captures) = pattern.captures(name) {
+        let replace = &captures[1] == "set";
+        let fixed: Option<Faction> = captures.get(2)
+            .and_then(|f| f.as_str().trim().chars().next())
+            .map(Faction);
+        let verb = if replace { "becomes" } else { "also becomes" };
+        return Some(
+            (
+                ActionMeta {
+                    description: match fixed {
+                        Some(f) => format!("This card {} faction {} this turn", verb, f),
+                        None => format!("Choose a faction. This card {} that faction this turn", verb)
+                    },
+                    config: match fixed {
+                        Some(_) => None,
+                        None => Some(Config {
+                            describe: Box::new(|i| format!("Faction #{}", i)),
+                            config_method: ActionConfigMethod::PickFaction(RelativePlayer::Current)
+                        })
+                    }
+                },
+                Box::new(move |game, id, cfg| {
+                    let factions = game.card_library.get_factions();
+                    let faction = match fixed {
+                        Some(f) => match factions.parse(&f.to_string()) {
+                            Ok(f) => f,
+                            Err(e) => return Fail(e)
+                        },
+                        None => match factions.all().get(cfg as usize) {
+                            Some(f) => *f,
+                            None => return Fail(format!("{} is not a faction", cfg))
+                        }
+                    };
+                    let player = game.get_current_player_mut();
+                    match player.get_card_in_hand_mut(id) {
+                        Some((_, card_status)) => {
+                            if replace {
+                                card_status.set_faction_for_turn(faction);
+                            } else {
+                                card_status.turn_factions.insert(faction);
+                            }
+                        }
+                        None => return Fail(format!("Card id {} is not in the hand", id))
+                    }
+                    // it counts as a card of its new faction played this turn
+                    player.update_counters();
+                    Succeed
+                })
+            )
+        )
+    }
+    None
+}
+
+fn acquire_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    if let Some((filter, to)) = parse_acquire(name) {
+        return Some(
+            (
+                ActionMeta {
+                    description: format!("Acquire a {} from the trade row for free, \
+                        and put it {}", filter.describe(), match to {
+                        AcquireTo::Discard => "in your discard pile",
+                        AcquireTo::TopOfDeck => "on top of your deck",
+                        AcquireTo::Hand => "into your hand"
+                    }),
+                    config: Some(Config {
+                        describe: Box::new(|i| format!("Trade row card #{}", i)),
+                        config_method: ActionConfigMethod::PickTradeRowCard(
+                            RelativePlayer::Current,
+                            filter.clone()
+                        )
+                    })
+                },
+                Box::new(move |game, _, cfg| {
+                    let choices = game.trade_row_choices(&filter);
+                    if choices.is_empty() {
+                        // nothing to acquire, the effect is wasted
+                        return Succeed;
+                    }
+                    if !choices.contains(&(cfg as usize)) {
+                        return Fail(format!("Trade row card #{} is not a {}", cfg, filter.describe()));
+                    }
+                    // the card was acquired either way, so skipped acquired effects don't undo it
+                    match game.acquire_from_trade_row(cfg as usize, to) {
+                        Ok(_) => Succeed,
+                        Err(e) => Fail(e)
+                    }
+                })
+            )
+        )
+    }
+    None
+}
+
+fn look_at_top_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    // ex. "look at top 3": look at the top 3 cards of your deck,
+    // discard any of them and put the rest back in any order
+    let n: u32 = name.strip_prefix("look at top ")?.parse().ok()?;
+    if n == 0 || n > MAX_ARRANGED {
+        return None;
+    }
+    Some(
+        (
+            ActionMeta {
+                description: format!("Look at the top {} cards of your deck. \
+                    Discard any of them and put the rest back in any order", n),
+                config: Some(Config {
+                    describe: Box::new(|i| format!("Put back {:?} (top first), discard the rest", decode_arrangement(i))),
+                    config_method: ActionConfigMethod::ArrangeTopOfDeck(n, RelativePlayer::Current)
+                })
+            },
+            Box::new(move |game, _, cfg| {
+                match game.get_current_player_mut().arrange_top_of_deck(n as usize, &decode_arrangement(cfg)) {
+                    Ok(()) => Succeed,
+                    Err(e) => Fail(e)
+                }
+            })
+        )
+    )
+}
+
+fn search_deck_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    // ex. "search deck for ship faction b": put one from your deck into your hand
+    let filter = parse_search(name)?;
+    Some(
+        (
+            ActionMeta {
+                description: format!("Search your deck for a {} and put it into your hand, \
+                    then shuffle your deck", filter.describe()),
+                config: Some(Config {
+                    describe: Box::new(|i| format!("Deck card #{}", i)),
+                    config_method: ActionConfigMethod::PickDeckCard(RelativePlayer::Current, filter.clone())
+                })
+            },
+            Box::new(move |game, _, cfg| {
+                let choices = game.deck_choices(&RelativePlayer::Current, &filter);
+                if choices.is_empty() {
+                    // nothing to find, the effect is wasted
+                    return Succeed;
+                }
+                if !choices.contains(&(cfg as usize)) {
+                    return Fail(format!("Deck card #{} is not a {}", cfg, filter.describe()));
+                }
+                match game.get_current_player_mut().search_deck(cfg as usize) {
+                    Ok(_) => Succeed,
+                    Err(e) => Fail(e)
+                }
+            })
+        )
+    )
+}
+
+fn destroy_base_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    match name {
+        // the rules let these ignore outposts
+        "destroy target base" => return Some(get_destroy_base_action(Opponent, false)),
+        "destroy target base outposts first" => return Some(get_destroy_base_action(Opponent, true)),
+        _ => ()
+    }
+    None
+}
+
+fn misc_action(name: &str, _: &EffectRegistry) -> Option<(ActionMeta, ActionFunc)> {
+    match name {
         "test" => Some(
             (
                 ActionMeta {
                     description: "test".to_string(),
                     config: None,
                 },
-                Box::new(|game: &mut GameState, _| {
-                    game.player1.discard.add(Card {
+                Box::new(|game: &mut GameState, _, _| {
+                    game.get_player_mut(Player(0)).discard.add(Card {
                         cost: 255,
                         name: String::from("bazinga"),
                         base: Some(Base::Outpost(4)),
+                        kind: CardKind::Deck,
                         synergizes_with: HashSet::new(),
                         effects: HashSet::new(),
                     });
@@ -290,172 +1070,142 @@ pub fn get_action(name: &String) -> Option<(ActionMeta, ActionFunc)> {
         "discard" => Some(
             (
                 ActionMeta {
-                    description: "opponent discards a card".to_string(),
-                    config: Some(Config {
-                        describe: Box::new(|_| "hand id of card to be discarded".to_string()),
-                        config_method: ActionConfigMethod::PickHandCard(Opponent, Opponent)
-                    })
+                    description: "opponent discards a card at the start of their turn".to_string(),
+                    config: None
                 },
-                Box::new(|game: &mut GameState, cfg| {
-                    let opponent = game.get_current_opponent_mut();
-                    match opponent.hand_id.get(&cfg) {
-                        None => Failure::Fail(format!("No card with id {}", &cfg)),
-                        Some((_, card_status)) => if card_status.in_play {
-                            Failure::Fail(
-                                format!("Card is in play, player must discard from hand \
-                                that has not been revealed"))
-                        } else if let Failure::Fail(msg) = opponent.discard_by_id(&cfg) {
-                            Failure::Fail(format!("unable to discard hand id {} in opponents hand: {}", &cfg, msg))
-                        } else {
-                            Succeed
-                        }
-                    }
-                })
-            )
-        ),
-        "destroy target base" => Some(
-            (
-                ActionMeta {
-                    description: "destroy any of the opponents bases".to_string(),
-                    config: Some(Config {
-                        describe: Box::new(|_| "hand id of the base to be destroyed".to_string()),
-                        config_method: ActionConfigMethod::PickHandCard(RelativePlayer::Current, RelativePlayer::Opponent)
-                    }),
-                },
-                Box::new(|game: &mut GameState, cfg| {
-                    let opponent = game.get_current_opponent_mut();
-                    match opponent.hand_id.get(&cfg) {
-                        None => Failure::Fail(format!("No card with id {}", &cfg)),
-                        Some((card, card_status)) => {
-                            if !&card_status.in_play {
-                                Failure::Fail(format!("Card {} must be in play!", &card.name))
-                            } else if let None = &card.base {
-                                Failure::Fail(format!("Card {} is not a base!", &card.name))
-                            } else {
-                                match opponent.discard_by_id(&cfg) {
-                                    Succeed => Succeed,
-                                    Failure::Fail(msg) => Failure::Fail(
-                                        format!("Unable to discard this card because: {}", msg))
-                                }
-                            }
-                        }
-                    }
+                Box::new(|game: &mut GameState, _, _| {
+                    // the opponent picks the card at the start of their turn
+                    game.get_current_opponent_mut().add_pending_discard();
+                    Succeed
                 })
             )
         ),
-        "stealth needle" => Some(
+        "copy ship" => Some(
             (
                 ActionMeta {
-                    description: "Copy any other ship in your hand".to_string(),
+                    description: "Copy another ship you played this turn".to_string(),
                     config: Some(Config {
-                        describe: Box::new(|_| "The card to copy".to_string()),
+                        describe: Box::new(|_| "The ship to copy".to_string()),
                         config_method: ActionConfigMethod::PickHandCard(
                             RelativePlayer::Current,
                             RelativePlayer::Current
                         )
                     })
                 },
-                Box::new(|game, cfg: HandId| {
-                    // turns out this is not actually a problem if you select another stealth
-                    // needle or itself
-                    // because even though you can theoretically get an infinite amount of
-                    // stealth needles, you cannot actually
-                    // increase the number of non-stealth needle
-                    // cards that you can copy
-                    // so it's not really a loophole
-                    // unless you crash the game from a memory overflow?
-                    let card = match game
-                        .get_current_player()
-                        .get_card_in_hand(&cfg) {
-                        Some((c, _)) => c,
+                Box::new(|game, id, cfg: HandId| {
+                    // copying another copying ship is fine: it gains whatever that ship
+                    // has gained, so nothing can be copied that wasn't played
+                    if *id == cfg {
+                        return Fail("A ship cannot copy itself".to_string());
+                    }
+                    let player = game.get_current_player();
+                    let (effects, factions) = match player.get_card_in_hand(&cfg) {
+                        Some((c, _)) if c.base.is_some() =>
+                            return Fail(format!("{} is not a ship", c.name)),
+                        Some((c, _)) if !player.played_this_turn(&cfg) =>
+                            return Fail(format!("{} was not played this turn", c.name)),
+                        Some((c, card_status)) => (card_status.effects(c), card_status.factions(c)),
                         None => return Fail("Not a valid id".to_string())
                     };
-                    let mut card = card.clone();
-                    card.synergizes_with.insert(Faction::Mech);
-                    let id = game.get_current_player_mut().give_card_to_hand(card);
-                    game.get_current_player_mut().plan_scrap(&id).unwrap();
-                    Succeed
-                })
-            )
-        ),
-        "acquire no cost" => Some(
-            (
-                ActionMeta {
-                    description: "Acquire any ship without paying \
-                        its cost and put it on top of your deck".to_string(),
-                    config: Some(Config {
-                        describe: Box::new(|_| "The ship to acquire".to_string()),
-                        config_method: ActionConfigMethod::Range(0, 4)
-                    })
-                },
-                Box::new(|game, cfg| {
-                    let cl = Rc::clone(&game.card_library);
-                    match game.trade_row.remove(cfg as usize) {
-                        Some(id) => {
-                            let card = cl.as_card(&id);
-                            if let None = card.base {
-                                game.get_current_player_mut().deck.add((*card).clone());
-                                Succeed
-                            } else {
-                                // make sure to add it back
-                                game.trade_row.add(id);
-                                Fail("Cannot be a base".to_string())
-                            }
+                    match game.get_current_player_mut().get_card_in_hand_mut(id) {
+                        Some((_, card_status)) => {
+                            card_status.gain_for_turn(effects, factions);
+                            Succeed
                         },
-                        None => Fail("Not a valid id".to_string())
+                        None => Fail(format!("The copying card ({}) does not exist", id))
                     }
                 })
             )
         ),
-        "merc cruiser" => Some(
-            (
-                ActionMeta {
-                    description: "Choose a faction as you play Merc Cruiser.\
-                     Merc Cruiser has that faction.".to_string(),
-                    config: Some(Config {
-                        describe: Box::new(|i| match i {
-                            0 => "Mech faction",
-                            1 => "Fed faction",
-                            2 => "Blob faction",
-                            3 | _ => "Star faction",
-                        }.to_string()),
-                        config_method: ActionConfigMethod::Range(0, 3)
-                    })
-                },
-                Box::new(|game, cfg| {
-                    let faction = match cfg {
-                        0 => Faction::Mech,
-                        1 => Faction::Fed,
-                        2 => Faction::Blob,
-                        3 | _ => Faction::Star
-                    };
-                    let card = Card {
-                        cost: 0,
-                        name: "synergy card".to_string(),
-
-                        base: None,
-                        synergizes_with: {
-                            let mut tmp = HashSet::new();
-                            tmp.insert(faction);
-                            tmp
-                        },
-                        effects: HashSet::new()
-                    };
-                    let id = game.get_current_player_mut().give_card_to_hand(card);
-                    game.get_current_player_mut().plan_scrap(&id).unwrap();
-                    Succeed
-                })
-                )
-        ),
         _ => None
     }
 }
 
+/// "acquire" followed by any of:
+///  - "ship" or "base"
+///  - "cost N", the most it can cost
+///  - "faction x"
+///  - "to top", "to hand" or "to discard" (default)
+///
+/// ex. "acquire ship to top"
+pub fn parse_acquire(name: &str) -> Option<(CardFilter, AcquireTo)> {
+    let mut words = name.split_whitespace();
+    if words.next() != Some("acquire") {
+        return None;
+    }
+    let mut filter = CardFilter::any();
+    let mut to = AcquireTo::Discard;
+    while let Some(word) = words.next() {
+        match word {
+            "to" => to = match words.next()? {
+                "top" => AcquireTo::TopOfDeck,
+                "hand" => AcquireTo::Hand,
+                "discard" => AcquireTo::Discard,
+                _ => return None
+            },
+            _ => parse_filter_word(&mut filter, word, &mut words)?
+        }
+    }
+    Some((filter, to))
+}
+
+/// ex. "ship faction b" or "base cost 4", the words after "search deck for"
+pub fn parse_search(name: &str) -> Option<CardFilter> {
+    let mut words = name.strip_prefix("search deck for ")?.split_whitespace();
+    let mut filter = CardFilter::any();
+    while let Some(word) = words.next() {
+        parse_filter_word(&mut filter, word, &mut words)?;
+    }
+    Some(filter)
+}
+
+/// one part of a `CardFilter`: "card", "ship", "base", "cost N" or "faction x"
+fn parse_filter_word<'a>(filter: &mut CardFilter, word: &str, words: &mut impl Iterator<Item=&'a str>) -> Option<()> {
+    match word {
+        "card" => (),
+        "ship" => filter.ships_only = true,
+        "base" => filter.bases_only = true,
+        "cost" => filter.max_cost = Some(words.next()?.parse().ok()?),
+        "faction" => {
+            let code = words.next()?;
+            if code.chars().count() != 1 {
+                return None;
+            }
+            filter.faction = code.chars().next().map(Faction);
+        }
+        _ => return None
+    }
+    Some(())
+}
+
+/// destroy one of `owner`'s bases in play (chosen by the current player)
+pub fn get_destroy_base_action(owner: RelativePlayer, respect_outposts: bool) -> (ActionMeta, ActionFunc) {
+    (
+        ActionMeta {
+            description: format!("destroy any of the {} player's bases{}", owner.to_string(),
+                                 if respect_outposts { ", outposts first" } else { "" }),
+            config: Some(Config {
+                describe: Box::new(|_| "hand id of the base to be destroyed".to_string()),
+                config_method: ActionConfigMethod::PickBase(RelativePlayer::Current, owner, respect_outposts)
+            }),
+        },
+        Box::new(move |game: &mut GameState, _, cfg| {
+            if game.resolve_relative_player(&owner).targetable_bases(respect_outposts).is_empty() {
+                // nothing to destroy, the effect is wasted
+                return Succeed;
+            }
+            match game.destroy_base(&owner, &cfg, respect_outposts) {
+                Ok(()) => Succeed,
+                Err(msg) => Fail(msg)
+            }
+        })
+    )
+}
+
+/// combat is saved up and spent in the combat phase
 pub fn get_good_action(goods: Goods) -> ActionFunc {
-    Box::new(move |game: &mut GameState, _| {
-        game.get_current_player_mut().goods.authority += goods.authority;
-        game.get_current_player_mut().goods.trade += goods.trade;
-        game.get_current_opponent_mut().goods.authority -= goods.combat;
+    Box::new(move |game: &mut GameState, _, _| {
+        game.gain_goods(game.current_player, goods);
         Succeed
     })
 }
@@ -466,23 +1216,22 @@ impl ActionMeta {
     }
 }
 
+/// goods saturate instead of overflowing
 impl Add for Goods {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
         Goods {
-            trade: self.trade + rhs.trade,
-            authority: self.authority + rhs.authority,
-            combat: self.combat + rhs.combat,
+            trade: self.trade.saturating_add(rhs.trade),
+            authority: self.authority.saturating_add(rhs.authority),
+            combat: self.combat.saturating_add(rhs.combat),
         }
     }
 }
 
 impl AddAssign for Goods {
     fn add_assign(&mut self, rhs: Self) {
-        self.trade += rhs.trade;
-        self.authority += rhs.authority;
-        self.combat += rhs.combat;
+        *self = *self + rhs;
     }
 }
 
@@ -3,25 +3,42 @@ extern crate regex;
 
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::fmt::{Display, Formatter};
+use std::convert::TryFrom;
 
 use components::stack::Stack;
 
 use crate::game::card_library::CardLibrary;
 use crate::game::components::{Authority, Coin, Combat};
-use crate::game::components::card::{Card, CardStatus};
+use crate::game::components::card::{Card, CardFilter, CardStatus};
+use crate::game::components::faction::Faction;
+use crate::game::components::scrap_heap::{ScrapHeap, ScrapSource};
+use crate::game::components::mission::Mission;
 use crate::game::util::Failure;
 
-use crate::game::effects::{ConfigSupplier, get_condition, get_action, Config, ActionConfigMethod, is_trash_cond};
+use crate::game::effects::{ConfigSupplier, Config, ActionConfigMethod, is_trash_cond,
+                          is_free_cond, is_event_cond, Trigger, get_trigger, get_objective,
+                          acquired_condition_met, get_placement};
 use crate::game::util::Failure::{Succeed, Fail};
 
 pub mod components;
 pub mod card_library;
 pub mod effects;
-mod util;
+pub mod effect_registry;
+pub mod script;
+pub mod util;
 
 type CardStack = Stack<Card>;
 pub type HandId = u32;
 
+pub const TRADE_ROW_SIZE: usize = 5;
+/// completing this many missions wins the game
+pub const MISSIONS_TO_WIN: usize = 3;
+/// everyone's authority, except for the raid boss and teams (see `GameState::starting_authority`)
+pub const STARTING_AUTHORITY: Authority = 50;
+/// each team's authority in team games
+pub const TEAM_AUTHORITY: Authority = 75;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Goods {
     pub(crate) trade: Coin,
@@ -29,21 +46,37 @@ pub struct Goods {
     pub(crate) combat: Combat,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PlayerArea {
     discard: CardStack,
     deck: CardStack,
     hand_id: HashMap<HandId, (Card, CardStatus)>, // all cards in hand or in play (including bases)
+    /// face-up gambits, which share ids with the hand
+    gambits: HashMap<HandId, (Card, CardStatus)>,
+    /// heroes in play, which also share ids with the hand
+    heroes: HashMap<HandId, (Card, CardStatus)>,
+    /// secret until they're completed
+    missions: Vec<Mission>,
+    completed_missions: Vec<Mission>,
     turn_data: TurnData,
-    scrapped: CardStack,
     goods: Goods,
+    /// cards this player has to discard at the start of their next turn
+    pending_discards: u32,
+    /// how many times hidden cards have been drawn (see `GameState::undo`)
+    reveals: u32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TurnData {
     to_be_scrapped: HashSet<HandId>,
     to_be_discarded: HashSet<HandId>,
-    played_this_turn: HashSet<HandId>
+    played_this_turn: HashSet<HandId>,
+    /// where the next card acquired this turn goes, instead of the discard pile
+    next_acquire_to: Option<AcquireTo>,
+    /// cards of each faction played this turn, see `Counter`
+    faction_plays: HashMap<Faction, u32>,
+    bases_in_play: u32,
+    ships_in_play: u32,
 }
 
 impl TurnData {
@@ -51,36 +84,60 @@ impl TurnData {
         TurnData {
             to_be_scrapped: HashSet::new(),
             to_be_discarded: HashSet::new(),
-            played_this_turn: HashSet::new()
+            played_this_turn: HashSet::new(),
+            next_acquire_to: None,
+            faction_plays: HashMap::new(),
+            bases_in_play: 0,
+            ships_in_play: 0,
         }
     }
     pub fn reset(&mut self)  {
         self.to_be_discarded = HashSet::new();
         self.to_be_scrapped = HashSet::new();
         self.played_this_turn = HashSet::new();
+        self.next_acquire_to = None;
+        self.faction_plays = HashMap::new();
+        // bases stay in play, `PlayerArea::update_counters` counts them again
+        self.bases_in_play = 0;
+        self.ships_in_play = 0;
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum Player {
-    Player1,
-    Player2,
-}
-impl Player {
-    pub fn reverse(&self) -> Player {
-        match self {
-            Player::Player1 => Player::Player2,
-            Player::Player2 => Player::Player1
-        }
+/// a player, by their place in the turn order (starting at 0)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Player(pub usize);
+
+impl Display for Player {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Player {}", self.0 + 1)
     }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RelativePlayer {
     Current,
+    /// the opponent that the current player is targeting (see `GameState::set_target`)
     Opponent
 }
 
+/// the official multiplayer formats
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameFormat {
+    /// anyone can attack anyone
+    FreeForAll,
+    /// you can only attack the authority of the player to your left (the next player),
+    /// but you can attack the bases of the players on either side of you
+    Hunter,
+    /// the first player is the boss, who starts with 50 authority for each other player.
+    /// the other players (raiders) can only attack the boss, and the boss can attack anyone.
+    Raid,
+    /// 2v2: the first two players are a team, and so are the last two.
+    /// teammates share a pool of 75 authority and take their turns together:
+    /// both of them play each phase before their team moves on to the next one.
+    /// they can attack the other team's bases and authority.
+    Teams,
+}
+
 impl RelativePlayer {
     pub fn to_string(&self) -> String {
         match self {
@@ -90,15 +147,165 @@ impl RelativePlayer {
     }
 }
 
+/// The phases of a turn, in order.
+/// After `Draw`, the turn goes to the next player and starts over at `StartOfTurn`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TurnPhase {
+    /// start-of-turn triggers, and discarding cards the opponent made you discard
+    StartOfTurn,
+    /// playing cards, using effects, and acquiring cards
+    Main,
+    /// spending combat on the opponent's bases and authority
+    Combat,
+    /// discarding (or scrapping) the ships played this turn
+    Discard,
+    /// drawing a new hand
+    Draw,
+}
+
+impl TurnPhase {
+    pub fn next(&self) -> TurnPhase {
+        match self {
+            TurnPhase::StartOfTurn => TurnPhase::Main,
+            TurnPhase::Main => TurnPhase::Combat,
+            TurnPhase::Combat => TurnPhase::Discard,
+            TurnPhase::Discard => TurnPhase::Draw,
+            TurnPhase::Draw => TurnPhase::StartOfTurn,
+        }
+    }
+}
+
+/// what combat can be spent on
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AttackTarget {
+    Authority(Player),
+    Base(Player, HandId),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CombatAction {
+    Attack(AttackTarget),
+    Undo,
+}
+
+#[derive(Clone)]
 pub struct GameState {
-    player1: PlayerArea,
-    player2: PlayerArea,
+    /// in turn order
+    players: Vec<PlayerArea>,
+    format: GameFormat,
     current_player: Player,
+    /// the opponent that the current player's effects are aimed at
+    target: Player,
+    phase: TurnPhase,
+    /// one authority pool per team in team games (see `GameState::change_authority`)
+    team_authority: Vec<Authority>,
     pub trade_row: Stack<u32>,
     pub explorers: u8,
-    pub scrapped: CardStack,
+    scrap_heap: ScrapHeap,
     pub trade_row_stack: Stack<u32>,
     pub card_library: Rc<CardLibrary>,
+    /// events that haven't been given to the client yet
+    events: Vec<GameEvent>,
+    /// play all of the free effects without choices at every step of the main phase
+    auto_play: bool,
+    /// counts changes to the game, so that steps that didn't change anything aren't undone
+    changes: u64,
+    /// the game before each step of this turn that can be undone, most recent last
+    undo_stack: Vec<GameState>,
+}
+
+/// where a newly acquired card goes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AcquireTo {
+    Discard,
+    TopOfDeck,
+    Hand,
+}
+
+/// the parts of a player's area that cards can be counted in (see `PlayerArea::cards_in_zone`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Zone {
+    /// ships played this turn, bases and heroes
+    InPlay,
+    /// cards in hand that haven't been played
+    InHand,
+    /// cards played this turn, not counting bases from earlier turns
+    PlayedThisTurn,
+    Discard,
+    Deck,
+}
+
+/// the numbers "for each" actions scale with, ex. "draw for each b card played this turn".
+/// They're kept up to date in `TurnData` as cards are played and leave play
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Counter {
+    /// cards of the faction played this turn, counting any factions they gained
+    FactionPlayed(Faction),
+    BasesInPlay,
+    ShipsInPlay,
+}
+
+/// things that happened in the game that clients might want to show
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    BaseDestroyed {
+        owner: Player,
+        by: Player,
+        base: Card,
+    },
+    /// the player revealed a mission whose objective they met
+    MissionCompleted {
+        player: Player,
+        mission: Mission,
+    },
+    /// the player's authority ran out
+    PlayerEliminated {
+        player: Player,
+        by: Player,
+    },
+    /// an event card was revealed in the trade row, resolved, and removed from the game
+    EventResolved {
+        card: Card,
+        /// effects that couldn't happen, and why
+        skipped: Vec<String>,
+    },
+}
+
+/// a summary of what would be wasted by ending a phase of the turn
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WastedResources {
+    /// (card name, effect) of effects that could still be used
+    pub unused_effects: Vec<(String, (String, String))>,
+    /// names of cards that haven't been played
+    pub unplayed_cards: Vec<String>,
+    pub trade: Coin,
+    pub combat: Combat,
+}
+
+impl WastedResources {
+    pub fn is_empty(&self) -> bool {
+        self.unused_effects.is_empty() && self.unplayed_cards.is_empty()
+            && self.trade == 0 && self.combat == 0
+    }
+}
+
+impl Display for WastedResources {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "These would be wasted:")?;
+        for (name, (cond, act)) in self.unused_effects.iter() {
+            writeln!(f, " - unused effect of {}: {} => {}", name, cond, act)?;
+        }
+        for name in self.unplayed_cards.iter() {
+            writeln!(f, " - unplayed card {}", name)?;
+        }
+        if self.trade > 0 {
+            writeln!(f, " - {} trade", self.trade)?;
+        }
+        if self.combat > 0 {
+            writeln!(f, " - {} combat", self.combat)?;
+        }
+        Ok(())
+    }
 }
 
 pub enum Feedback {
@@ -113,9 +320,19 @@ pub enum UserActionIntent<T> {
 
 pub enum AbstractPlayerAction {
     CardEffects,
+    /// use every free effect that doesn't need any choices
+    PlayAllFree,
+    /// do `PlayAllFree` automatically during the main phase
+    ToggleAutoPlay,
+    /// pick which opponent effects are aimed at
+    TargetOpponent,
+    /// complete a mission whose objective has been met
+    RevealMission,
     TradeRow,
     TrashCard,
     EndTurn,
+    /// take back the last action this turn
+    Undo,
 }
 
 pub trait UserActionSupplier {
@@ -127,22 +344,41 @@ pub trait UserActionSupplier {
     /// return 0 to attempt to buy an explorer
     fn select_trade_row_card(&self, game: &GameState) -> UserActionIntent<HandId>;
 
+    /// the index of one of the current player's missions
+    fn select_mission(&self, game: &GameState) -> UserActionIntent<usize>;
+
+    /// one of `GameState::targetable_opponents`
+    fn select_opponent(&self, game: &GameState) -> UserActionIntent<Player>;
+
+    /// during the combat phase, Cancel to stop attacking
+    fn select_combat_action(&self, game: &GameState) -> UserActionIntent<CombatAction>;
+
     fn on_feedback(&self, feedback: Feedback);
+
+    /// answer a yes or no question
+    fn confirm(&self, game: &GameState, question: &str) -> bool;
+
+    fn on_event(&self, event: &GameEvent);
 }
 
 impl PlayerArea {
-    pub fn new(scout: Card, viper: Card, first: bool) -> PlayerArea {
+    pub fn new(scout: Card, viper: Card, hand_size: u8) -> PlayerArea {
         let mut pa = PlayerArea {
             discard: CardStack::empty(),
             deck: CardStack::empty(),
-            scrapped: CardStack::empty(),
             hand_id: HashMap::new(),
+            gambits: HashMap::new(),
+            heroes: HashMap::new(),
+            missions: vec![],
+            completed_missions: vec![],
             goods: Goods {
                 combat: 0,
-                authority: 50,
+                authority: STARTING_AUTHORITY,
                 trade: 0
             },
-            turn_data: TurnData::new()
+            turn_data: TurnData::new(),
+            pending_discards: 0,
+            reveals: 0
         };
         for _ in 0..8 {
             pa.deck.add(scout.clone());
@@ -151,12 +387,7 @@ impl PlayerArea {
             pa.deck.add(viper.clone());
         }
         pa.deck.shuffle();
-        if first {
-            // todo: hardcoded
-            pa.draw_hand(3);
-        } else {
-            pa.draw_hand(5);
-        }
+        pa.draw_hand(hand_size);
         pa
     }
 
@@ -191,23 +422,189 @@ impl PlayerArea {
         ids
     }
 
+    pub fn get_goods(&self) -> &Goods {
+        &self.goods
+    }
+    pub fn get_pending_discards(&self) -> u32 {
+        self.pending_discards
+    }
+    /// the player will have to discard a card at the start of their next turn
+    pub fn add_pending_discard(&mut self) {
+        self.pending_discards += 1;
+    }
+    /// cards in hand that haven't been played, so they can be discarded
+    pub fn unplayed_hand_card_ids(&self) -> HashSet<HandId> {
+        self.hand_id.iter()
+            .filter(|(_, (_, card_status))| !card_status.in_play)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+    /// discard a card that hasn't been played, resolving one pending discard
+    pub fn resolve_pending_discard(&mut self, id: &HandId) -> Result<(), String> {
+        if self.pending_discards == 0 {
+            return Err("There are no cards to discard".to_string());
+        }
+        if !self.unplayed_hand_card_ids().contains(id) {
+            return Err(format!("Card {} is in play or not in the hand, it cannot be discarded", id));
+        }
+        if let Failure::Fail(msg) = self.discard_by_id(id) {
+            return Err(msg);
+        }
+        self.pending_discards -= 1;
+        Ok(())
+    }
+
     pub fn get_card_in_hand(&self, id: &HandId) -> Option<&(Card, CardStatus)> {
         self.hand_id.get(id)
     }
     pub fn get_card_in_hand_mut(&mut self, id: &HandId) -> Option<&mut (Card, CardStatus)> {
         self.hand_id.get_mut(id)
     }
+    /// a card in the hand, in play, in the gambit zone or with the heroes
+    pub fn get_card(&self, id: &HandId) -> Option<&(Card, CardStatus)> {
+        self.hand_id.get(id)
+            .or_else(|| self.gambits.get(id))
+            .or_else(|| self.heroes.get(id))
+    }
+    pub fn get_card_mut(&mut self, id: &HandId) -> Option<&mut (Card, CardStatus)> {
+        if self.hand_id.contains_key(id) {
+            self.hand_id.get_mut(id)
+        } else if self.gambits.contains_key(id) {
+            self.gambits.get_mut(id)
+        } else {
+            self.heroes.get_mut(id)
+        }
+    }
+    /// every card whose effects can be used: cards in hand or in play, then gambits, then heroes
+    pub fn effect_cards(&self) -> impl Iterator<Item=(&HandId, &(Card, CardStatus))> {
+        self.hand_id.iter().chain(self.gambits.iter()).chain(self.heroes.iter())
+    }
+    /// ids of all of the cards in `effect_cards`
+    pub fn get_all_card_ids(&self) -> HashSet<HandId> {
+        self.effect_cards().map(|(id, _)| *id).collect()
+    }
+    pub fn get_gambit_ids(&self) -> Vec<HandId> {
+        let mut ids: Vec<_> = self.gambits.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+    /// put a gambit face-up in front of the player
+    pub fn add_gambit(&mut self, card: Card) -> HandId {
+        let id = self.get_unused_hand_id();
+        let mut card_status = CardStatus::new();
+        card_status.reveal();
+        self.gambits.insert(id, (card, card_status));
+        id
+    }
+    pub fn get_hero_ids(&self) -> Vec<HandId> {
+        let mut ids: Vec<_> = self.heroes.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+    /// put a hero into play, where it stays until it's scrapped
+    pub fn add_hero(&mut self, card: Card) -> HandId {
+        let id = self.get_unused_hand_id();
+        let mut card_status = CardStatus::new();
+        card_status.reveal();
+        self.heroes.insert(id, (card, card_status));
+        self.update_counters();
+        id
+    }
+    /// missions that haven't been completed yet
+    pub fn get_missions(&self) -> &[Mission] {
+        &self.missions
+    }
+    pub fn get_completed_missions(&self) -> &[Mission] {
+        &self.completed_missions
+    }
+    pub fn give_mission(&mut self, mission: Mission) {
+        self.missions.push(mission);
+    }
+    /// cards that have trash effects and haven't been scrapped yet
+    pub fn scrappable_card_ids(&self) -> Vec<HandId> {
+        let mut ids: Vec<_> = self.effect_cards()
+            .filter(|(_, (card, card_status))| !card_status.scrapped
+                && card_status.effects(card).iter().any(|(c, _)| is_trash_cond(c)))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+    /// consume an effect of this card, which also plays the card
+    pub fn use_effect(&mut self, id: &HandId, effect: &(String, String)) -> Result<(), String> {
+        match self.get_card_mut(id) {
+            Some((_, card_status)) => {
+                card_status.use_effect(effect);
+                self.turn_data.played_this_turn.insert(*id);
+                self.update_counters();
+                Ok(())
+            }
+            None => Err(format!("Card id {} is not in the hand", id))
+        }
+    }
+    pub fn played_this_turn(&self, id: &HandId) -> bool {
+        self.turn_data.played_this_turn.contains(id)
+    }
+    pub fn counter(&self, counter: Counter) -> u32 {
+        match counter {
+            Counter::FactionPlayed(faction) => self.turn_data.faction_plays.get(&faction).copied().unwrap_or(0),
+            Counter::BasesInPlay => self.turn_data.bases_in_play,
+            Counter::ShipsInPlay => self.turn_data.ships_in_play,
+        }
+    }
+    /// counts `TurnData`'s counters again, after a card is played, leaves play, or changes faction
+    fn update_counters(&mut self) {
+        let mut faction_plays = HashMap::new();
+        let (mut bases, mut ships) = (0, 0);
+        for (id, (card, card_status)) in self.effect_cards() {
+            if self.played_this_turn(id) {
+                for faction in card_status.factions(card) {
+                    *faction_plays.entry(faction).or_insert(0) += 1;
+                }
+            }
+            if card_status.in_play {
+                if card.base.is_some() {
+                    bases += 1;
+                } else if card.is_ship() {
+                    ships += 1;
+                }
+            }
+        }
+        self.turn_data.faction_plays = faction_plays;
+        self.turn_data.bases_in_play = bases;
+        self.turn_data.ships_in_play = ships;
+    }
+    /// every card in the zone, with its factions (including any gained this turn)
+    pub fn cards_in_zone(&self, zone: Zone) -> Vec<(&Card, HashSet<Faction>)> {
+        let in_area = |f: &dyn Fn(&HandId, &CardStatus) -> bool| self.effect_cards()
+            .filter(|(id, (_, card_status))| f(id, card_status))
+            .map(|(_, (card, card_status))| (card, card_status.factions(card)))
+            .collect();
+        fn in_stack(stack: &CardStack) -> Vec<(&Card, HashSet<Faction>)> {
+            stack.iter().map(|card| (card, card.synergizes_with.clone())).collect()
+        }
+        match zone {
+            Zone::InPlay => in_area(&|_, card_status| card_status.in_play),
+            Zone::InHand => in_area(&|id, card_status| !card_status.in_play && self.hand_id.contains_key(id)),
+            Zone::PlayedThisTurn => in_area(&|id, _| self.played_this_turn(id)),
+            Zone::Discard => in_stack(&self.discard),
+            Zone::Deck => in_stack(&self.deck),
+        }
+    }
+    /// whether any other card in play (ships played this turn, bases or heroes) has this faction
+    pub fn has_ally(&self, id: &HandId, faction: &Faction) -> bool {
+        self.effect_cards().any(|(other, (card, card_status))| {
+            other != id && card_status.in_play && card_status.factions(card).contains(faction)
+        })
+    }
     pub fn draw_hand(&mut self, num_cards: u8) {
-        let mut id_index = 0;
         for _ in 0..num_cards {
             let card = self.draw();
             if let None = card {
                 break;
             }
             let card = card.unwrap();
-            while self.hand_id.contains_key(&id_index) {
-                id_index += 1;
-            }
+            let id_index = self.get_unused_hand_id();
             let is_ship = card.base.is_none();
             self.hand_id.insert(id_index.clone(), (card, CardStatus::new()));
             if is_ship {
@@ -217,23 +614,26 @@ impl PlayerArea {
     }
     pub fn draw_into_hand(&mut self) {
         if let Some(card) = self.draw() {
-            let id = self.get_unused_hand_id();
-            self.hand_id.insert(id, (card, CardStatus::new()));
+            self.give_card_to_hand(card);
         }
     }
     fn get_unused_hand_id(&self) -> HandId {
         let mut id_index = 0;
-        while self.hand_id.contains_key(&id_index) {
+        while self.get_card(&id_index).is_some() {
             id_index += 1;
         }
         id_index
     }
 
-    pub fn end_turn(&mut self) {
+    /// Returns the cards that were scrapped this turn.
+    /// They should be handed over to the game's scrap heap (see `GameState::scrap_card`)
+    pub fn end_turn(&mut self) -> Vec<Card> {
+        let mut scrapped = vec![];
         let to_be_scrapped = self.turn_data.to_be_scrapped.clone();
         for id in to_be_scrapped {
-            if let Failure::Fail(err) = self.scrap_by_id(&id) {
-                panic!("PlayerArea::end_turn: {}", err);
+            match self.take_by_id(&id) {
+                Ok(card) => scrapped.push(card),
+                Err(err) => panic!("PlayerArea::end_turn: {}", err)
             }
         }
         let to_be_discarded = self.turn_data.to_be_discarded.clone();
@@ -250,9 +650,15 @@ impl PlayerArea {
             }
             card_status.reset_base();
         }
+        // gambits' and heroes' effects can be used again every turn until they're scrapped
+        for (_, card_status) in self.gambits.values_mut().chain(self.heroes.values_mut()) {
+            card_status.reset_base();
+        }
         self.goods.trade = 0;
-        self.goods.combat = 0; // todo: aggregate combat then deal it at the end of the turn
+        self.goods.combat = 0; // combat that wasn't spent in the combat phase is lost
         self.turn_data.reset();
+        self.update_counters();
+        scrapped
     }
 
     /// Discard this card at the end of the turn.
@@ -273,7 +679,7 @@ impl PlayerArea {
     }
 
     pub fn plan_scrap(&mut self, id: &HandId) -> Result<(), String> {
-        if self.hand_id.contains_key(id) {
+        if self.get_card(id).is_some() {
             if self.turn_data.to_be_discarded.contains(id) {
                 Err("This card is going to be discarded, it cannot be scrapped.".to_string())
             } else {
@@ -285,25 +691,62 @@ impl PlayerArea {
         }
     }
 
+    /// bases in play that can be targeted.
+    /// if `respect_outposts` is true and there are outposts, only outposts can be targeted
+    pub fn targetable_bases(&self, respect_outposts: bool) -> Vec<HandId> {
+        let outposts_only = respect_outposts && self.has_outpost_in_play();
+        let mut ids: Vec<_> = self.hand_id.iter()
+            .filter(|(_, (card, card_status))| match &card.base {
+                Some(base) => card_status.in_play && (!outposts_only || base.is_outpost()),
+                None => false
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    pub fn has_outpost_in_play(&self) -> bool {
+        self.hand_id.values().any(|(card, card_status)| {
+            card_status.in_play && card.base.as_ref().is_some_and(|b| b.is_outpost())
+        })
+    }
+
     pub fn discard_by_id(&mut self, id: &HandId) -> Failure<String> {
+        // whatever was planned for this card this turn no longer applies
+        self.turn_data.to_be_discarded.remove(id);
+        self.turn_data.to_be_scrapped.remove(id);
+        self.turn_data.played_this_turn.remove(id);
         match self.hand_id.remove(id) {
             Some((card, _)) => {
                 self.discard.add(card);
+                self.update_counters();
                 Failure::Succeed
             },
             None => Failure::Fail(format!("cannot discard card by id {}!", id))
         }
     }
-    pub fn scrap_by_id(&mut self, id: &HandId) -> Failure<String> {
-        match self.hand_id.remove(id) {
+    /// Scrap this card at the end of the turn, even if it was going to be discarded.
+    pub fn force_plan_scrap(&mut self, id: &HandId) -> Result<(), String> {
+        self.turn_data.to_be_discarded.remove(id);
+        self.plan_scrap(id)
+    }
+
+    /// take the card out of the hand (or play) entirely, so that it can be scrapped
+    pub fn take_by_id(&mut self, id: &HandId) -> Result<Card, String> {
+        match self.hand_id.remove(id)
+            .or_else(|| self.gambits.remove(id))
+            .or_else(|| self.heroes.remove(id)) {
             Some((card, _)) => {
-                self.scrapped.add(card);
-                Failure::Succeed
-            },
-            None => Failure::Fail(format!("cannot scrap card by id {}!", id))
+                self.update_counters();
+                Ok(card)
+            }
+            None => Err(format!("cannot scrap card by id {}!", id))
         }
     }
     fn draw(&mut self) -> Option<Card> {
+        // drawing (or shuffling) reveals something that can't be taken back
+        self.reveals += 1;
         if let Some(c) = self.deck.draw() {
             Some(c)
         } else {
@@ -319,9 +762,91 @@ impl PlayerArea {
             }
         }
     }
+    /// Shuffle the discard pile in under the deck if there are fewer than `n` cards in it,
+    /// the same as drawing would once the deck ran out
+    pub fn refill_deck(&mut self, n: usize) {
+        if self.deck.len() >= n || self.discard.len() == 0 {
+            return;
+        }
+        self.reveals += 1;
+        self.discard.shuffle();
+        let left = self.deck.draw_top(n);
+        while let Some(c) = self.discard.draw() {
+            self.deck.add(c);
+        }
+        self.deck.put_on_top(left);
+    }
+    /// the top `n` cards of the deck, starting with the top one.
+    /// (fewer if the deck runs out, see `refill_deck`)
+    pub fn top_of_deck(&self, n: usize) -> Vec<&Card> {
+        self.deck.peek_top(n)
+    }
+    /// Put the cards at these indexes of `top_of_deck(n)` back on the deck, the first on top,
+    /// and discard the rest of them. The deck is refilled first, if it has to be
+    pub fn arrange_top_of_deck(&mut self, n: usize, order: &[usize]) -> Result<(), String> {
+        self.refill_deck(n);
+        let n = n.min(self.deck.len());
+        let unique: HashSet<_> = order.iter().collect();
+        if unique.len() < order.len() || order.iter().any(|&i| i >= n) {
+            return Err(format!("{:?} is not an order for the top {} cards", order, n));
+        }
+        self.reveals += 1;
+        let mut top: Vec<_> = self.deck.draw_top(n).into_iter().map(Some).collect();
+        let kept = order.iter().filter_map(|&i| top[i].take()).collect();
+        self.deck.put_on_top(kept);
+        for card in top.into_iter().flatten() {
+            self.discard.add(card);
+        }
+        Ok(())
+    }
+    /// indexes of the cards in the deck that match the filter, starting from the top
+    /// (see `GameState::deck_choices`)
+    pub fn deck_choices(&self, filter: &CardFilter) -> Vec<usize> {
+        self.deck.positions(|card| filter.matches(card))
+    }
+    pub fn get_card_in_deck(&self, index: usize) -> Option<&Card> {
+        self.deck.peek(index)
+    }
+    /// take a card out of the deck and into the hand, then shuffle the deck
+    pub fn search_deck(&mut self, index: usize) -> Result<HandId, String> {
+        let card = self.deck.remove(index).ok_or(format!("There is no card #{} in the deck", index))?;
+        self.reveals += 1;
+        self.deck.shuffle();
+        Ok(self.give_card_to_hand(card))
+    }
+    pub fn set_next_acquire_to(&mut self, to: AcquireTo) {
+        self.turn_data.next_acquire_to = Some(to);
+    }
+    /// where a card acquired to `to` ends up, using up `set_next_acquire_to` for cards
+    /// that would have been discarded
+    pub fn acquire_destination(&mut self, to: AcquireTo) -> AcquireTo {
+        match to {
+            AcquireTo::Discard => self.turn_data.next_acquire_to.take().unwrap_or(to),
+            _ => to
+        }
+    }
+    /// heroes always go into play, wherever they were meant to go
+    pub fn gain_card(&mut self, card: Card, to: AcquireTo) {
+        if card.is_hero() {
+            self.add_hero(card);
+            return;
+        }
+        match to {
+            AcquireTo::Discard => self.discard.add(card),
+            AcquireTo::TopOfDeck => self.deck.add(card),
+            AcquireTo::Hand => {
+                self.give_card_to_hand(card);
+            }
+        }
+    }
+    /// ships are planned to be discarded, just like when drawing a hand
     pub fn give_card_to_hand (&mut self, card: Card) -> HandId {
         let id = self.get_unused_hand_id();
-        self.hand_id.insert(id.clone(), (card, CardStatus::new()));
+        let is_ship = card.base.is_none();
+        self.hand_id.insert(id, (card, CardStatus::new()));
+        if is_ship {
+            self.plan_discard(&id).unwrap();
+        }
         id
     }
 }
@@ -330,25 +855,144 @@ impl GameState {
     /// panics if there is no scout or viper
     /// this is helpful https://www.starrealms.com/sets-and-expansions/
     pub fn new (card_library: Rc<CardLibrary>) -> GameState {
+        GameState::with_players(card_library, 2, GameFormat::FreeForAll)
+            .expect("a two player free-for-all is always possible")
+    }
+
+    /// Err if there are too few players for the format
+    pub fn with_players(card_library: Rc<CardLibrary>, num_players: usize, format: GameFormat)
+        -> Result<GameState, String> {
+        let min_players = match format {
+            GameFormat::FreeForAll => 2,
+            GameFormat::Hunter | GameFormat::Raid => 3,
+            GameFormat::Teams => 4,
+        };
+        if num_players < min_players {
+            return Err(format!("{:?} needs at least {} players", format, min_players));
+        }
+        if format == GameFormat::Teams && num_players != 4 {
+            return Err("Teams are played 2v2".to_string());
+        }
         let scout = card_library.get_scout().expect("card library needs a scout!");
         let viper = card_library.get_viper().expect("card library needs a viper!");
+        let players = (0..num_players).map(|i| {
+            // the first players get smaller hands to make up for going first
+            let hand_size = match (i, num_players) {
+                (0, _) => 3,
+                (1, 2) => 5,
+                (1, _) => 4,
+                _ => 5
+            };
+            PlayerArea::new((*scout).clone(), (*viper).clone(), hand_size)
+        }).collect();
+        let team_authority = match format {
+            GameFormat::Teams => vec![TEAM_AUTHORITY; num_players / 2],
+            _ => vec![]
+        };
         let mut gs = GameState {
-            player1: PlayerArea::new((*scout).clone(), (*viper).clone(), true),
-            player2: PlayerArea::new((*scout).clone(), (*viper).clone(), false),
-            current_player: Player::Player1,
+            players,
+            format,
+            current_player: Player(0),
+            target: Player(1),
+            phase: TurnPhase::StartOfTurn,
+            team_authority,
             trade_row: Stack::empty(),
             explorers: 10,
-            scrapped: CardStack::empty(),
+            scrap_heap: ScrapHeap::new(),
             trade_row_stack: {
                 let mut stack = Stack::new(card_library.get_new_trade_stack());
                 stack.shuffle();
                 stack
             },
             card_library: Rc::clone(&card_library),
+            events: vec![],
+            auto_play: false,
+            changes: 0,
+            undo_stack: vec![],
         };
-        // todo: number of cards in trade row hard-coded
-        gs.fill_trade_row(5);
-        gs
+        for i in 0..num_players {
+            // each teammate sees their team's pool
+            gs.players[i].goods.authority = gs.starting_authority(Player(i));
+        }
+        gs.deal_trade_row();
+        gs.target = gs.targetable_opponents()[0];
+        Ok(gs)
+    }
+
+    /// Setup for the Gambit expansion: deal each player `per_player` random gambits, face-up.
+    /// Err if the card library doesn't have enough of them
+    pub fn deal_gambits(&mut self, per_player: usize) -> Result<(), String> {
+        let mut gambits = Stack::new(self.card_library.get_gambits());
+        let needed = per_player * self.players.len();
+        if gambits.len() < needed {
+            return Err(format!("{} gambits are needed, but there are only {}", needed, gambits.len()));
+        }
+        gambits.shuffle();
+        for player in self.players.iter_mut() {
+            for _ in 0..per_player {
+                let id = gambits.draw().unwrap();
+                player.add_gambit(self.card_library.as_new_card(&id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Setup for missions: deal each player `per_player` random missions, in secret.
+    /// Err if the card library doesn't have enough of them
+    pub fn deal_missions(&mut self, per_player: usize) -> Result<(), String> {
+        let mut missions = Stack::new(self.card_library.get_missions().to_vec());
+        let needed = per_player * self.players.len();
+        if missions.len() < needed {
+            return Err(format!("{} missions are needed, but there are only {}", needed, missions.len()));
+        }
+        missions.shuffle();
+        for player in self.players.iter_mut() {
+            for _ in 0..per_player {
+                player.give_mission(missions.draw().unwrap());
+            }
+        }
+        Ok(())
+    }
+
+    /// whether the objective of the current player's mission is met
+    pub fn mission_objective_met(&self, index: usize) -> bool {
+        match self.get_current_player().missions.get(index) {
+            Some(mission) => get_objective(&mission.objective)
+                .is_some_and(|objective| objective(self, self.current_player)),
+            None => false
+        }
+    }
+
+    /// Complete one of the current player's missions and get its reward.
+    /// Err if the objective hasn't been met
+    fn reveal_mission<T>(&mut self, client: &T, index: usize) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        if !self.mission_objective_met(index) {
+            return Err("The mission's objective hasn't been met".to_string());
+        }
+        let mission = self.get_current_player().missions[index].clone();
+        let (meta, mut reward) = self.card_library.get_effects().get_action(&mission.reward)
+            .ok_or(format!("{} has a bad reward {}", mission.name, mission.reward))?;
+        let config = match &meta.config {
+            Some(config) => self.get_config(client, config),
+            None => 0
+        };
+        // missions aren't cards, so there's no card id
+        if let Fail(msg) = reward(self, &HandId::MAX, config) {
+            return Err(format!("Unable to reward the mission {}. {}", mission.name, msg));
+        }
+        // only completed once it's been rewarded
+        let player = self.get_current_player_mut();
+        player.missions.remove(index);
+        player.completed_missions.push(mission.clone());
+        // the mission was secret, so this can't be taken back
+        player.reveals += 1;
+        self.changes += 1;
+        self.events.push(GameEvent::MissionCompleted {
+            player: self.current_player,
+            mission: mission.clone()
+        });
+        Ok(format!("Completed the mission {}", mission.name))
     }
 
     pub fn from_config(config_folder: &str) -> Result<GameState, String> {
@@ -356,14 +1000,153 @@ impl GameState {
         Ok(GameState::new(Rc::new(cl)))
     }
 
-    fn fill_trade_row(&mut self, num: usize) {
-        let left = num - self.trade_row.len();
-        for _ in 0..left {
+    /// the first trade row: events that turn up are shuffled back in instead of resolving
+    fn deal_trade_row(&mut self) {
+        let mut events = vec![];
+        while self.trade_row.len() < TRADE_ROW_SIZE {
             match self.trade_row_stack.draw() {
                 None => break,
+                Some(id) if self.card_library.as_card(&id).is_event() => events.push(id),
                 Some(id) => self.trade_row.add(id)
             }
         }
+        if !events.is_empty() {
+            for id in events {
+                self.trade_row_stack.add(id);
+            }
+            self.trade_row_stack.shuffle();
+        }
+    }
+
+    /// events are resolved as they're drawn, and more cards are drawn to take their place
+    fn fill_trade_row(&mut self, num: usize) {
+        while self.trade_row.len() < num {
+            match self.trade_row_stack.draw() {
+                None => break,
+                Some(id) => {
+                    let card = self.card_library.as_card(&id);
+                    if card.is_event() {
+                        self.resolve_event(&card);
+                    } else {
+                        self.trade_row.add(id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run all of the event's effects, then it's gone.
+    /// Effects that can't happen (ex. drawing from an empty deck) are skipped,
+    /// and their reasons go along with the `EventResolved` event.
+    fn resolve_event(&mut self, card: &Card) {
+        let mut effects: Vec<_> = card.effects.iter()
+            .filter(|(cond, _)| is_event_cond(cond))
+            .collect();
+        effects.sort();
+        let mut skipped = vec![];
+        for (_, act) in effects {
+            match self.card_library.get_effects().get_action(act) {
+                Some((_, mut action)) => {
+                    // events aren't in anyone's area, so there's no card id
+                    if let Fail(msg) = action(self, &HandId::MAX, 0) {
+                        skipped.push(format!("Unable to resolve {} for the event {}. {}", act, card.name, msg));
+                    }
+                }
+                None => skipped.push(format!("Unknown effect {} for the event {}", act, card.name))
+            }
+        }
+        self.changes += 1;
+        self.events.push(GameEvent::EventResolved { card: card.clone(), skipped });
+    }
+
+    /// Do something as each living player in turn order, starting with the current player.
+    /// Each of them targets the first opponent they can while it's their go.
+    pub(crate) fn for_each_player<F>(&mut self, mut f: F) -> Failure<String>
+        where F: FnMut(&mut GameState) -> Failure<String> {
+        let (current, target) = (self.current_player, self.target);
+        let mut player = current;
+        let mut result = Succeed;
+        loop {
+            self.current_player = player;
+            if let Some(target) = self.targetable_opponents().first() {
+                self.target = *target;
+            }
+            if let Fail(msg) = f(self) {
+                result = Fail(msg);
+                break;
+            }
+            player = self.next_living(player);
+            if player == current {
+                break;
+            }
+        }
+        self.current_player = current;
+        self.target = target;
+        result
+    }
+
+    /// indices of the trade row cards that match the filter
+    pub fn trade_row_choices(&self, filter: &CardFilter) -> Vec<usize> {
+        let filter = self.canonical_filter(filter);
+        self.trade_row.iter()
+            .enumerate()
+            .filter(|(_, id)| filter.matches(&self.card_library.as_card(id)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// indexes of the cards in `player`'s deck that match the filter, starting from the top
+    pub fn deck_choices(&self, player: &RelativePlayer, filter: &CardFilter) -> Vec<usize> {
+        self.resolve_relative_player(player).deck_choices(&self.canonical_filter(filter))
+    }
+
+    /// the filter's faction could be an alias, ex. "faction y"
+    fn canonical_filter(&self, filter: &CardFilter) -> CardFilter {
+        CardFilter {
+            faction: filter.faction.map(|f| self.card_library.get_factions().canonical(f)),
+            ..filter.clone()
+        }
+    }
+
+    /// Give the card at `index` of the trade row to the current player (without paying for it)
+    /// and refill the trade row.
+    /// Err means nothing happened. Once the card is taken, acquired effects that can't happen
+    /// are skipped (like events), and their reasons are returned along with the card.
+    pub fn acquire_from_trade_row(&mut self, index: usize, to: AcquireTo) -> Result<(Rc<Card>, Vec<String>), String> {
+        let id = *self.trade_row.peek(index)
+            .ok_or(format!("{} is not a valid index in the trade row", index))?;
+        let card = self.card_library.as_card(&id);
+        let mut acquired: Vec<_> = card.effects.iter()
+            .filter(|(cond, _)| get_trigger(cond) == Some(Trigger::Acquired)
+                && acquired_condition_met(self, cond))
+            .map(|(_, act)| act.clone())
+            .collect();
+        acquired.sort();
+        // the card's own placement wins over `next acquire` effects
+        let mut to = self.get_current_player_mut().acquire_destination(to);
+        for act in acquired.iter() {
+            if let Some(placement) = get_placement(act) {
+                to = placement;
+            }
+        }
+        let mut actions = vec![];
+        for act in acquired.iter().filter(|act| get_placement(act).is_none()) {
+            let (_, action) = self.card_library.get_effects().get_action(act)
+                .ok_or(format!("Unknown effect {} when acquiring {}", act, card.name))?;
+            actions.push((act, action));
+        }
+        self.trade_row.remove(index);
+        self.get_current_player_mut().gain_card((*card).clone(), to);
+        let mut skipped = vec![];
+        for (act, mut action) in actions {
+            // the card isn't in the player's area yet, so there's no card id
+            if let Fail(msg) = action(self, &HandId::MAX, 0) {
+                skipped.push(format!("Unable to resolve {} when acquiring {}. {}", act, card.name, msg));
+            }
+        }
+        self.fill_trade_row(TRADE_ROW_SIZE);
+        self.changes += 1;
+        Ok((card, skipped))
     }
 
     pub fn unpack_multi_trade_row_card_selection(&self, bits: &u32) -> HashSet<u32> {
@@ -378,29 +1161,388 @@ impl GameState {
     }
 
     /// ids: the indices of the cards to be removed
-    pub fn remove_cards_from_trade_row(&mut self, ids: HashSet<u32>) -> HashSet<Card> {
+    pub fn remove_cards_from_trade_row(&mut self, ids: HashSet<u32>) -> Vec<Card> {
         let mut ids: Vec<_> = ids.iter().collect();
         ids.sort();
         ids.reverse(); // remove them from biggest to smallest to prevent shifting
-        let mut cards = HashSet::new();
+        // not a set: the trade row can hold more than one copy of a card
+        let mut cards = vec![];
         for i in ids {
             let id = self.trade_row.remove(*i as usize)
                 .ok_or(format!("{} is not a valid index in the trade row", i)).unwrap();
-            cards.insert((*self.card_library.get_card_by_id(&id).unwrap()).clone());
+            cards.push((*self.card_library.get_card_by_id(&id).unwrap()).clone());
         }
         cards
     }
 
+    /// Destroy one of `owner`'s bases, putting it in their discard pile.
+    /// When `respect_outposts` is true, outposts have to be destroyed before any other base.
+    pub fn destroy_base(&mut self, owner: &RelativePlayer, id: &HandId, respect_outposts: bool) -> Result<(), String> {
+        let player = self.resolve_relative_player(owner);
+        if !player.targetable_bases(respect_outposts).contains(id) {
+            return Err(match player.get_card_in_hand(id) {
+                Some((card, _)) if card.base.is_none() => format!("Card {} is not a base!", &card.name),
+                Some((card, card_status)) if !card_status.in_play => format!("Card {} must be in play!", &card.name),
+                Some((card, _)) => format!("Card {} cannot be targeted while there is an outpost", &card.name),
+                None => format!("No card with id {}", id)
+            });
+        }
+        let base = player.get_card_in_hand(id).unwrap().0.clone();
+        if let Fail(msg) = self.resolve_relative_player_mut(owner).discard_by_id(id) {
+            return Err(format!("Unable to discard this card because: {}", msg));
+        }
+        self.events.push(GameEvent::BaseDestroyed {
+            owner: self.resolve_relative(owner),
+            by: self.current_player,
+            base
+        });
+        Ok(())
+    }
+
+    /// events that happened since the last time this was called
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn get_scrap_heap(&self) -> &ScrapHeap {
+        &self.scrap_heap
+    }
+
+    /// Put a card on the scrap heap.
+    /// Explorers are returned to the explorer pile instead, as the rules require.
+    pub fn scrap_card(&mut self, card: Card, by: Player, from: ScrapSource) {
+        let is_explorer = match self.card_library.get_explorer() {
+            Some(explorer) => explorer.name == card.name,
+            None => false
+        };
+        if is_explorer {
+            self.explorers += 1;
+        } else {
+            self.scrap_heap.add(card, by, from);
+        }
+    }
+
+    /// end the current player's turn, sending their scrapped cards to the scrap heap
+    fn end_current_turn(&mut self) {
+        let player = self.current_player;
+        let scrapped = self.get_current_player_mut().end_turn();
+        for card in scrapped {
+            self.scrap_card(card, player, ScrapSource::Hand);
+        }
+    }
+
+    pub fn get_phase(&self) -> TurnPhase {
+        self.phase
+    }
+
+    /// Move on to the next phase, handing the turn over to the next player after `Draw`.
+    /// In team games, the second teammate plays each phase after the first one,
+    /// then the first one starts the next phase
+    fn next_phase(&mut self) {
+        let teammates = self.teammates(self.current_player);
+        if let Some(next) = teammates.iter().skip_while(|p| **p != self.current_player).nth(1) {
+            self.hand_over(*next);
+            self.changes += 1;
+            return;
+        }
+        if self.phase == TurnPhase::Draw {
+            self.flip_turn();
+        } else {
+            self.hand_over(teammates[0]);
+        }
+        self.phase = self.phase.next();
+        if self.phase == TurnPhase::Discard {
+            // the turn is over, it can't be taken back anymore
+            self.undo_stack.clear();
+        }
+        self.changes += 1;
+    }
+
+    fn snapshot(&mut self) -> GameState {
+        // don't copy the whole history into every snapshot
+        let undo_stack = std::mem::take(&mut self.undo_stack);
+        let snapshot = self.clone();
+        self.undo_stack = undo_stack;
+        snapshot
+    }
+
+    /// go back to a snapshot from this step, keeping the undo history
+    fn restore(&mut self, mut snapshot: GameState) {
+        snapshot.undo_stack = std::mem::take(&mut self.undo_stack);
+        *self = snapshot;
+    }
+
+    /// changes whenever hidden information (cards in decks) is revealed
+    fn hidden_info_marker(&self) -> (Vec<u32>, usize) {
+        (self.players.iter().map(|p| p.reveals).collect(), self.trade_row_stack.len())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Go back to before the last step of this turn that changed anything.
+    /// Steps that drew or shuffled cards (or revealed new trade row cards) can't be undone,
+    /// and neither can anything before them.
+    pub fn undo(&mut self) -> Result<(), String> {
+        match self.undo_stack.pop() {
+            Some(mut previous) => {
+                previous.undo_stack = std::mem::take(&mut self.undo_stack);
+                previous.auto_play = self.auto_play;
+                previous.changes = self.changes;
+                *self = previous;
+                Ok(())
+            }
+            None => Err("There is nothing to undo this turn".to_string())
+        }
+    }
+
+    /// Spend the current player's combat on the target.
+    /// Outposts have to be destroyed before other bases or authority can be attacked.
+    pub fn attack(&mut self, target: AttackTarget) -> Result<(), String> {
+        let combat = self.get_current_player().goods.combat;
+        match target {
+            AttackTarget::Authority(player) => {
+                if !self.attackable_authorities().contains(&player) {
+                    return Err(format!("{} cannot be attacked", player));
+                }
+                if self.get_player(player).has_outpost_in_play() {
+                    return Err("Outposts have to be destroyed before attacking authority".to_string());
+                }
+                let damage = Authority::try_from(combat).unwrap_or(Authority::MAX);
+                self.change_authority(player, damage.saturating_neg());
+                self.get_current_player_mut().goods.combat = 0;
+                if self.is_eliminated(player) {
+                    for player in self.teammates(player) {
+                        self.events.push(GameEvent::PlayerEliminated {
+                            player,
+                            by: self.current_player
+                        });
+                    }
+                }
+                self.changes += 1;
+                Ok(())
+            }
+            AttackTarget::Base(player, id) => {
+                if !self.attackable_base_owners().contains(&player) {
+                    return Err(format!("{}'s bases cannot be attacked", player));
+                }
+                let defense = match self.get_player(player).get_card_in_hand(&id) {
+                    Some((Card { base: Some(base), .. }, _)) => base.defense(),
+                    _ => return Err(format!("{} is not one of {}'s bases", id, player))
+                };
+                if defense > combat {
+                    return Err(format!("Not enough combat to destroy this base: {} > {}", defense, combat));
+                }
+                let target = self.target;
+                self.target = player;
+                let destroyed = self.destroy_base(&RelativePlayer::Opponent, &id, true);
+                self.target = target;
+                destroyed?;
+                self.get_current_player_mut().goods.combat -= defense;
+                self.changes += 1;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_format(&self) -> GameFormat {
+        self.format
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn get_player(&self, player: Player) -> &PlayerArea {
+        &self.players[player.0]
+    }
+
+    pub fn get_player_mut(&mut self, player: Player) -> &mut PlayerArea {
+        &mut self.players[player.0]
+    }
+
+    pub fn get_current_player_id(&self) -> Player {
+        self.current_player
+    }
+
+    /// the players on `player`'s team, including them.
+    /// everyone is on their own team outside of team games.
+    pub fn teammates(&self, player: Player) -> Vec<Player> {
+        match self.format {
+            GameFormat::Teams => {
+                let first = player.0 - player.0 % 2;
+                vec![Player(first), Player(first + 1)]
+            }
+            _ => vec![player]
+        }
+    }
+
+    /// the raid boss starts with 50 authority for each raider, and each team has its own pool
+    pub fn starting_authority(&self, player: Player) -> Authority {
+        match self.format {
+            GameFormat::Raid if self.is_raid_boss(player) =>
+                STARTING_AUTHORITY * (self.players.len() as Authority - 1),
+            GameFormat::Teams => TEAM_AUTHORITY,
+            _ => STARTING_AUTHORITY
+        }
+    }
+
+    /// shared between teammates in team games
+    pub fn authority_of(&self, player: Player) -> Authority {
+        match self.format {
+            GameFormat::Teams => self.team_authority[player.0 / 2],
+            _ => self.get_player(player).goods.authority
+        }
+    }
+
+    /// Every change to authority goes through here, so that in team games
+    /// the team's pool changes and both teammates' goods show it
+    pub(crate) fn change_authority(&mut self, player: Player, by: Authority) {
+        let authority = self.authority_of(player).saturating_add(by);
+        if self.format == GameFormat::Teams {
+            self.team_authority[player.0 / 2] = authority;
+        }
+        for teammate in self.teammates(player) {
+            self.get_player_mut(teammate).goods.authority = authority;
+        }
+    }
+
+    /// add goods to the player's, with any authority going through `change_authority`
+    pub(crate) fn gain_goods(&mut self, player: Player, goods: Goods) {
+        self.get_player_mut(player).goods += Goods { authority: 0, ..goods };
+        self.change_authority(player, goods.authority);
+    }
+
+    pub fn is_eliminated(&self, player: Player) -> bool {
+        self.authority_of(player) <= 0
+    }
+
+    /// players that haven't been eliminated, in turn order
+    pub fn living_players(&self) -> Vec<Player> {
+        (0..self.players.len())
+            .map(Player)
+            .filter(|p| !self.is_eliminated(*p))
+            .collect()
+    }
+
+    /// the next living player after `player` in turn order (to their left)
+    fn next_living(&self, player: Player) -> Player {
+        let n = self.players.len();
+        (1..n).map(|i| Player((player.0 + i) % n))
+            .find(|p| !self.is_eliminated(*p))
+            .unwrap_or(player)
+    }
+
+    /// the previous living player before `player` in turn order (to their right)
+    fn previous_living(&self, player: Player) -> Player {
+        let n = self.players.len();
+        (1..n).map(|i| Player((player.0 + n - i) % n))
+            .find(|p| !self.is_eliminated(*p))
+            .unwrap_or(player)
+    }
+
+    /// in raids, the boss is the first player
+    fn is_raid_boss(&self, player: Player) -> bool {
+        self.format == GameFormat::Raid && player == Player(0)
+    }
+
+    /// players whose authority the current player can attack
+    pub fn attackable_authorities(&self) -> Vec<Player> {
+        let current = self.current_player;
+        let mut players: Vec<_> = match self.format {
+            GameFormat::FreeForAll => self.living_players(),
+            GameFormat::Hunter => vec![self.next_living(current)],
+            GameFormat::Raid if self.is_raid_boss(current) => self.living_players(),
+            GameFormat::Raid => self.living_players().into_iter()
+                .filter(|p| self.is_raid_boss(*p))
+                .collect(),
+            GameFormat::Teams => self.living_players().into_iter()
+                .filter(|p| !self.teammates(current).contains(p))
+                .collect()
+        };
+        players.retain(|p| *p != current);
+        players
+    }
+
+    /// players whose bases the current player can attack
+    pub fn attackable_base_owners(&self) -> Vec<Player> {
+        let current = self.current_player;
+        let mut players = match self.format {
+            GameFormat::Hunter => vec![self.next_living(current), self.previous_living(current)],
+            _ => self.attackable_authorities()
+        };
+        players.retain(|p| *p != current);
+        players.sort();
+        players.dedup();
+        players
+    }
+
+    /// the opponents that effects can be aimed at
+    pub fn targetable_opponents(&self) -> Vec<Player> {
+        self.attackable_base_owners()
+    }
+
+    pub fn get_target(&self) -> Player {
+        self.target
+    }
+
+    /// aim the current player's effects at this opponent
+    pub fn set_target(&mut self, player: Player) -> Result<(), String> {
+        if self.targetable_opponents().contains(&player) {
+            self.target = player;
+            Ok(())
+        } else {
+            Err(format!("{} cannot be targeted", player))
+        }
+    }
+
+    /// None while the game is still going.
+    /// In raids, all of the raiders win together, and teammates win together.
+    /// Completing `MISSIONS_TO_WIN` missions also wins.
+    pub fn winners(&self) -> Option<Vec<Player>> {
+        let living = self.living_players();
+        // completing enough missions is another way to win
+        if let Some(player) = living.iter()
+            .find(|p| self.get_player(**p).completed_missions.len() >= MISSIONS_TO_WIN) {
+            return Some(self.teammates(*player));
+        }
+        if self.format == GameFormat::Raid {
+            let boss = Player(0);
+            if self.is_eliminated(boss) {
+                return Some((1..self.players.len()).map(Player).collect());
+            }
+            if living == vec![boss] {
+                return Some(living);
+            }
+            return None;
+        }
+        match living.first() {
+            Some(first) if living.iter().any(|p| !self.teammates(*first).contains(p)) => None,
+            _ => Some(living)
+        }
+    }
+
+    /// hand the turn to the next living player (or team), who targets the first opponent they can
     fn flip_turn(&mut self) {
-        self.current_player = match self.current_player {
-            Player::Player1 => Player::Player2,
-            Player::Player2 => Player::Player1
+        self.hand_over(self.next_living(self.current_player));
+    }
+
+    /// let `player` act, targeting the first opponent they can
+    fn hand_over(&mut self, player: Player) {
+        if player != self.current_player {
+            // nobody can undo someone else's actions
+            self.undo_stack.clear();
+        }
+        self.current_player = player;
+        if let Some(target) = self.targetable_opponents().first() {
+            self.target = *target;
         }
     }
     pub fn resolve_relative(&self, relative_player: &RelativePlayer) -> Player {
         match relative_player {
-            RelativePlayer::Current => self.current_player.clone(),
-            RelativePlayer::Opponent => self.current_player.reverse()
+            RelativePlayer::Current => self.current_player,
+            RelativePlayer::Opponent => self.target
         }
     }
     pub fn resolve_relative_player(&self, relative_player: &RelativePlayer) -> &PlayerArea {
@@ -417,37 +1559,17 @@ impl GameState {
         }
     }
     pub fn get_current_player(&self) -> &PlayerArea {
-        match &self.current_player {
-            Player::Player1 => &self.player1,
-            Player::Player2 => &self.player2,
-        }
+        self.get_player(self.current_player)
     }
     pub fn get_current_player_mut(&mut self) -> &mut PlayerArea {
-        match &self.current_player {
-            Player::Player1 => &mut self.player1,
-            Player::Player2 => &mut self.player2,
-        }
+        self.get_player_mut(self.current_player)
     }
+    /// the opponent being targeted
     pub fn get_current_opponent(&self) -> &PlayerArea {
-        match &self.current_player {
-            Player::Player1 => &self.player2,
-            Player::Player2 => &self.player1
-        }
+        self.get_player(self.target)
     }
     pub fn get_current_opponent_mut(&mut self) -> &mut PlayerArea {
-        match &self.current_player {
-            Player::Player1 => &mut self.player2,
-            Player::Player2 => &mut self.player1
-        }
-    }
-    pub fn turn_is_player1(&self) -> bool {
-        match &self.current_player {
-            Player::Player1 => true,
-            Player::Player2 => false
-        }
-    }
-    pub fn turn_is_player2(&self) -> bool {
-        !self.turn_is_player1()
+        self.get_player_mut(self.target)
     }
 
 
@@ -456,59 +1578,352 @@ impl GameState {
     ///     this case is RECOVERABLE (the function can be run again)
     pub fn advance<T>(&mut self, client: &T) -> Result<String, String>
         where T: ConfigSupplier + UserActionSupplier {
-        println!("current player: {:?}", self.current_player);
+        let result = self.advance_action(client);
+        for event in self.take_events() {
+            client.on_event(&event);
+        }
+        result
+    }
+
+    fn advance_action<T>(&mut self, client: &T) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        if self.winners().is_some() {
+            return Ok("The game is over".to_string());
+        }
+        println!("current player: {} ({:?} phase)", self.current_player, self.phase);
         println!("{:?}", self.get_current_player().goods);
 
+        let undoable = match self.phase {
+            TurnPhase::Main | TurnPhase::Combat => Some(self.snapshot()),
+            _ => None
+        };
+        let marker = self.hidden_info_marker();
+        let changes = self.changes;
+        let result = self.advance_phase(client);
+        if self.hidden_info_marker() != marker {
+            // something was revealed, so nothing before this can be undone
+            self.undo_stack.clear();
+        } else if let Some(snapshot) = undoable {
+            if self.changes != changes {
+                self.undo_stack.push(snapshot);
+            }
+        }
+        result
+    }
+
+    fn advance_phase<T>(&mut self, client: &T) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        match self.phase {
+            TurnPhase::StartOfTurn => self.start_of_turn(client),
+            TurnPhase::Main => self.main_phase(client),
+            TurnPhase::Combat => self.combat_phase(client),
+            TurnPhase::Discard => {
+                self.resolve_triggers(client, Trigger::EndOfTurn);
+                self.end_current_turn();
+                self.next_phase();
+                Ok("Played cards were discarded".to_string())
+            }
+            TurnPhase::Draw => {
+                self.get_current_player_mut().draw_hand(5);
+                self.next_phase();
+                Ok("Turn was ended".to_string())
+            }
+        }
+    }
+
+    fn start_of_turn<T>(&mut self, client: &T) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        // triggers are consumed when they run, so they only run once even if this phase repeats
+        self.resolve_triggers(client, Trigger::StartOfTurn);
+        while self.get_current_player().pending_discards > 0 {
+            if self.get_current_player().unplayed_hand_card_ids().is_empty() {
+                // nothing left to discard
+                self.get_current_player_mut().pending_discards = 0;
+                break;
+            }
+            let card_id = client.get_config(self, &Config {
+                describe: Box::new(|_| "The card to be discarded".to_string()),
+                config_method: ActionConfigMethod::PickHandCard(
+                    RelativePlayer::Current,
+                    RelativePlayer::Current
+                )
+            });
+            if let Err(msg) = self.get_current_player_mut().resolve_pending_discard(&card_id) {
+                client.on_feedback(Feedback::Invalid(msg.clone()));
+                return Ok(msg);
+            }
+        }
+        self.next_phase();
+        Ok("Turn was started".to_string())
+    }
+
+    fn combat_phase<T>(&mut self, client: &T) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        if self.get_current_player().goods.combat == 0 {
+            self.next_phase();
+            return Ok("Combat phase was skipped, there is no combat".to_string());
+        }
+        match client.select_combat_action(self) {
+            UserActionIntent::Continue(CombatAction::Attack(target)) => match self.attack(target) {
+                Ok(()) => Ok(format!("Attacked {:?}", target)),
+                Err(msg) => {
+                    client.on_feedback(Feedback::Invalid(msg.clone()));
+                    Ok(msg)
+                }
+            },
+            UserActionIntent::Continue(CombatAction::Undo) => self.undo_for(client),
+            UserActionIntent::Cancel => {
+                if !self.confirm_waste(client, "Stop attacking anyway?") {
+                    return Ok("Canceled ending the combat phase".to_string());
+                }
+                self.next_phase();
+                Ok("Combat phase was ended".to_string())
+            }
+        }
+    }
+
+    /// Ask the client for a config, once the game is ready for it
+    /// (ex. the deck is refilled before anyone looks at the top of it)
+    fn get_config<T>(&mut self, client: &T, config: &Config) -> u32 where T: ConfigSupplier {
+        if let ActionConfigMethod::ArrangeTopOfDeck(n, by) = &config.config_method {
+            self.resolve_relative_player_mut(by).refill_deck(*n as usize);
+        }
+        client.get_config(self, config)
+    }
+
+    /// run the action of this effect, then consume the effect.
+    /// (the condition should already have been checked)
+    fn run_action<T>(&mut self, client: &T, card_id: &HandId, (cond_s, act_s): (String, String))
+        -> Result<String, String> where T: ConfigSupplier + UserActionSupplier {
+        let (action_meta, mut action_func) = self.card_library.get_effects().get_action(&act_s)
+            .ok_or_else(|| format!("GameState.advance(): bad selection action {}. \
+                It might be a good idea to validate cards before hand.", &act_s))?;
+        let config = match &action_meta.config {
+            Some(config) => self.get_config(client, config),
+            _ => 0,
+        };
+        match action_func(self, card_id, config) {
+            // if the action fails, then a bad config was passed in.
+            // perhaps we can report these better
+            Fail(msg) => Err(
+                format!(
+                    "Unable to complete action to {}. {}",
+                    action_meta.description.clone(),
+                    msg)
+            ),
+            // if it succeeds, make sure to consume the effect
+            Succeed => {
+                self.changes += 1;
+                match self.get_current_player_mut().use_effect(card_id, &(cond_s, act_s)) {
+                    Ok(()) => Ok("Effect was used and consumed".to_string()),
+                    Err(_) => Err(
+                        format!(
+                            "Card id {} is not one of {:?}",
+                            card_id,
+                            self.get_current_player().get_all_card_ids()))
+                }
+            }
+        }
+    }
+
+    /// Run every unused effect with this trigger on the current player's cards in play.
+    /// Any choices are made by the current player, since they own the cards.
+    /// An effect that fails is reported and skipped, so the phase can still go on
+    fn resolve_triggers<T>(&mut self, client: &T, trigger: Trigger)
+        where T: ConfigSupplier + UserActionSupplier {
+        let mut triggered = vec![];
+        for (id, (card, card_status)) in self.get_current_player().effect_cards() {
+            if !card_status.in_play {
+                continue;
+            }
+            for effect in card_status.unused_effects(card) {
+                if get_trigger(&effect.0) == Some(trigger) {
+                    triggered.push((*id, effect));
+                }
+            }
+        }
+        // resolve them in a consistent order
+        triggered.sort();
+        for (id, effect) in triggered {
+            self.run_or_skip(client, &id, effect);
+        }
+    }
+
+    /// Run the action of an effect that nobody has to pick, or report it if it fails.
+    /// Either way the effect is used up, so it isn't tried again.
+    /// Returns whether it ran
+    fn run_or_skip<T>(&mut self, client: &T, card_id: &HandId, effect: (String, String)) -> bool
+        where T: ConfigSupplier + UserActionSupplier {
+        match self.run_action(client, card_id, effect.clone()) {
+            Ok(_) => true,
+            Err(msg) => {
+                client.on_feedback(Feedback::Invalid(msg));
+                if let Some((_, card_status)) = self.get_current_player_mut().get_card_mut(card_id) {
+                    card_status.use_effect(&effect);
+                }
+                false
+            }
+        }
+    }
+
+    /// What would be wasted by moving on from the current phase.
+    /// Combat isn't wasted by ending the main phase, since it's spent in the combat phase,
+    /// and effects can't be used after the main phase, so they only count during it.
+    pub fn wasted_resources(&self) -> WastedResources {
+        let player = self.get_current_player();
+        let mut wasted = WastedResources {
+            unused_effects: vec![],
+            unplayed_cards: vec![],
+            trade: 0,
+            combat: 0,
+        };
+        match self.phase {
+            TurnPhase::Main => {
+                for (id, (card, card_status)) in player.effect_cards() {
+                    if !card_status.in_play {
+                        wasted.unplayed_cards.push(card.name.clone());
+                        continue;
+                    }
+                    for effect in card_status.unused_effects(card) {
+                        // effects that can't be used right now aren't being wasted
+                        let usable = match self.card_library.get_effects().get_condition(&effect.0) {
+                            Some(mut cond) => cond(self, id),
+                            None => false
+                        };
+                        if usable {
+                            wasted.unused_effects.push((card.name.clone(), effect));
+                        }
+                    }
+                }
+                wasted.trade = player.goods.trade;
+            }
+            TurnPhase::Combat => wasted.combat = player.goods.combat,
+            _ => ()
+        }
+        wasted.unused_effects.sort();
+        wasted.unplayed_cards.sort();
+        wasted
+    }
+
+    /// Tell the client about anything that would be wasted, and ask them to confirm.
+    /// true if there's nothing to waste, or they confirmed
+    fn confirm_waste<T>(&self, client: &T, question: &str) -> bool
+        where T: ConfigSupplier + UserActionSupplier {
+        let wasted = self.wasted_resources();
+        if wasted.is_empty() {
+            return true;
+        }
+        client.on_feedback(Feedback::Info(wasted.to_string()));
+        client.confirm(self, question)
+    }
+
+    pub fn is_auto_play(&self) -> bool {
+        self.auto_play
+    }
+
+    pub fn set_auto_play(&mut self, auto_play: bool) {
+        self.auto_play = auto_play;
+    }
+
+    /// `free` effects of the current player's cards that can be used without making any choices
+    pub fn choice_free_effects(&self) -> Vec<(HandId, (String, String))> {
+        let mut effects = vec![];
+        for (id, (card, card_status)) in self.get_current_player().effect_cards() {
+            for effect in card_status.unused_effects(card) {
+                let no_config = match self.card_library.get_effects().get_action(&effect.1) {
+                    Some((meta, _)) => meta.no_config(),
+                    None => false
+                };
+                if is_free_cond(&effect.0) && no_config {
+                    effects.push((*id, effect));
+                }
+            }
+        }
+        effects.sort();
+        effects
+    }
+
+    /// Use every effect from `choice_free_effects`, including those of any cards drawn along the way.
+    /// Effects that fail are reported and skipped.
+    /// Returns the number of effects used.
+    pub fn play_all_free<T>(&mut self, client: &T) -> usize
+        where T: ConfigSupplier + UserActionSupplier {
+        let mut used = 0;
+        loop {
+            let effects = self.choice_free_effects();
+            if effects.is_empty() {
+                return used;
+            }
+            for (id, effect) in effects {
+                if self.run_or_skip(client, &id, effect) {
+                    used += 1;
+                }
+            }
+        }
+    }
+
+    fn undo_for<T>(&mut self, client: &T) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        match self.undo() {
+            Ok(()) => Ok("Undid the last action".to_string()),
+            Err(msg) => {
+                client.on_feedback(Feedback::Invalid(msg.clone()));
+                Ok(msg)
+            }
+        }
+    }
+
+    fn main_phase<T>(&mut self, client: &T) -> Result<String, String>
+        where T: ConfigSupplier + UserActionSupplier {
+        if self.auto_play {
+            self.play_all_free(client);
+        }
         let next = client.choose_abstract_action(self);
         match next {
+            AbstractPlayerAction::PlayAllFree => {
+                let used = self.play_all_free(client);
+                Ok(format!("Used {} free effects", used))
+            }
+            AbstractPlayerAction::Undo => self.undo_for(client),
+            AbstractPlayerAction::TargetOpponent => match client.select_opponent(self) {
+                UserActionIntent::Continue(player) => match self.set_target(player) {
+                    Ok(()) => Ok(format!("Now targeting {}", player)),
+                    Err(msg) => {
+                        client.on_feedback(Feedback::Invalid(msg.clone()));
+                        Ok(msg)
+                    }
+                },
+                UserActionIntent::Cancel => Ok("Canceled targeting an opponent".to_string())
+            },
+            AbstractPlayerAction::RevealMission => match client.select_mission(self) {
+                UserActionIntent::Continue(index) => if self.mission_objective_met(index) {
+                    self.reveal_mission(client, index)
+                } else {
+                    let s = "The mission's objective hasn't been met".to_string();
+                    client.on_feedback(Feedback::Invalid(s.clone()));
+                    Ok(s)
+                },
+                UserActionIntent::Cancel => Ok("Canceled revealing a mission".to_string())
+            },
+            AbstractPlayerAction::ToggleAutoPlay => {
+                self.auto_play = !self.auto_play;
+                Ok(format!("Auto play is {}", if self.auto_play { "on" } else { "off" }))
+            }
             AbstractPlayerAction::CardEffects =>
                 if let UserActionIntent::Continue((card_id, (cond_s, act_s)))
             // select to either exit, or continue with an effect
                 = client.select_effect(self) {
                 // then parse the condition and action of this effect
-                let mut cond = get_condition(cond_s.clone())
+                let mut cond = self.card_library.get_effects().get_condition(&cond_s)
                     .expect(
                         format!(
                             "GameState.advance(): bad selection condition {}. \
                         It might be a good idea to validate cards before hand.", &cond_s)
                             .as_str());
-                let (action_meta, mut action_func) = get_action(&act_s)
-                    .expect(
-                        format!("GameState.advance(): bad selection action {}. \
-                        It might be a good idea to validate cards before hand.", &act_s)
-                            .as_str());
                 // evaluate the condition
                 if cond(self, &card_id) {
                     // if true, run the action
-                    // println!("cond succeeded! running action...");
-                    match action_func(self,
-                                      match action_meta.config {
-                                          Some(config) => client.get_config(self, &config),
-                                          _ => 0,
-                                      }) {
-                        // if the action fails, then a bad config was passed in.
-                        // perhaps we can report these better
-                        Fail(msg) => Err(
-                            format!(
-                                "Unable to complete action to {}. {}",
-                                action_meta.description.clone(),
-                                msg)
-                        ),
-                        // if it succeeds, make sure to consume the effect
-                        Succeed => {
-                            match self.get_current_player_mut().get_card_in_hand_mut(&card_id) {
-                                Some((_, card_status)) => {
-                                    card_status.use_effect(&(cond_s, act_s));
-                                    Ok("Effect was used and consumed".to_string())
-                                }
-                                None => Err(
-                                    format!(
-                                        "Card id {} is not one of {:?}",
-                                        card_id,
-                                        self.get_current_player().hand_id.keys()))
-                            }
-                        }
-                    }
+                    self.run_action(client, &card_id, (cond_s, act_s))
                 } else {
                     // if the condition is not true, report the mistake to the client, or user?
                     let s = "This effect is not possible at the moment.".to_string();
@@ -534,8 +1949,11 @@ impl GameState {
                                 Ok("Cannot buy explorer".to_string())
                             } else {
                                 self.explorers -= 1;
+                                self.changes += 1;
                                 self.get_current_player_mut().goods.trade -= explorer.cost;
-                                self.get_current_player_mut().discard.add(explorer);
+                                let player = self.get_current_player_mut();
+                                let to = player.acquire_destination(AcquireTo::Discard);
+                                player.gain_card(explorer, to);
                                 Ok("Bought an explorer".to_string())
                             }
                         }
@@ -545,12 +1963,12 @@ impl GameState {
                     if let Some(card_id) = card_id {
                         let card = self.card_library.as_card(card_id);
                         if card.cost <= self.get_current_player().goods.trade {
-                            self.trade_row.remove(index as usize).unwrap();
-                            let success_message = format!("{:?} acquired {}", self.current_player, &card.name);
-                            let player = self.get_current_player_mut();
-                            player.goods.trade -= card.cost;
-                            player.discard.add((*card).clone());
-                            Ok(success_message)
+                            self.get_current_player_mut().goods.trade -= card.cost;
+                            let (_, skipped) = self.acquire_from_trade_row(index as usize, AcquireTo::Discard)?;
+                            for msg in skipped {
+                                client.on_feedback(Feedback::Invalid(msg));
+                            }
+                            Ok(format!("{} acquired {}", self.current_player, &card.name))
                         } else {
                             let s = format!("Cannot purchase card {} since the cost is more \
                                 trade than the current player owns. {} > {}", card.name, card.cost,
@@ -567,27 +1985,32 @@ impl GameState {
                 }
             AbstractPlayerAction::EndTurn => {
                 // the client chooses to exit, and hand over the turn.
-                // todo: warn them if they haven't completed all their effects with Feedback::Info
-                //     and client.on_feedback()
                 // todo: automatically exit turn if all effects have been completed
-                self.get_current_player_mut().end_turn();
-                self.get_current_player_mut().draw_hand(5);
-                self.flip_turn();
-                Ok("Turn was ended".to_string())
+                if !self.confirm_waste(client, "End the turn anyway?") {
+                    return Ok("Canceled ending the turn".to_string());
+                }
+                self.next_phase();
+                Ok("Main phase was ended".to_string())
             }
             AbstractPlayerAction::TrashCard => {
+                if self.get_current_player().scrappable_card_ids().is_empty() {
+                    let s = "None of your cards can be scrapped".to_string();
+                    client.on_feedback(Feedback::Invalid(s.clone()));
+                    return Ok(s);
+                }
                 let card_id = client.get_config(self, &Config {
                     describe: Box::new(|_| "The card to be scrapped".to_string()),
-                    config_method: ActionConfigMethod::PickHandCard(
-                        RelativePlayer::Current,
-                        RelativePlayer::Current
-                    )
+                    config_method: ActionConfigMethod::PickScrappableCard(RelativePlayer::Current)
                 });
-                let (card, card_status) = self.get_current_player_mut()
-                    .get_card_in_hand_mut(&card_id)
+                let player = self.get_current_player_mut();
+                let (card, card_status) = player
+                    .get_card_mut(&card_id)
                     .ok_or(format!("Client: supplied bad card id {}", &card_id)).unwrap();
-                if card.effects.iter().any(|(c, _)| is_trash_cond(c)) {
+                if card_status.effects(card).iter().any(|(c, _)| is_trash_cond(c)) {
                     card_status.scrapped = true;
+                    // the card leaves play at the end of the turn
+                    player.force_plan_scrap(&card_id)?;
+                    self.changes += 1;
                     client.on_feedback(
                         Feedback::Info(
                             "This card's trash effect can now be used.".to_string()));
@@ -0,0 +1,269 @@
+extern crate rhai;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use self::rhai::{Engine, EvalAltResult, Scope, AST, INT};
+
+use crate::game::{GameState, Goods, HandId, RelativePlayer};
+use crate::game::components::faction::{Faction, Factions};
+use crate::game::components::Authority;
+use crate::game::effects::{ActionConfigMethod, ActionFunc, ActionMeta, Config, ConditionFunc};
+use crate::game::util::Failure::{Fail, Succeed};
+
+/// Effects written as Rhai scripts (https://rhai.rs) instead of the keywords in `effects`.
+/// Actions start with "script ", ex. `free: "script gain(2, 0, 0); if hand_size() == 0 { draw(1) }"`,
+/// and so do conditions, ex. `"script bases_in_play() >= 2": G0.0.3`.
+///
+/// Scripts can read the game through:
+///  - `authority()`, `opponent_authority()`, `trade()`, `combat()`
+///  - `hand_size()`, the cards in hand that haven't been played
+///  - `bases_in_play()`, `ships_in_play()`, `cards_in_play(faction)`, ex. `cards_in_play("b")`
+///  - `has_ally(faction)`, whether another card of that faction is in play
+///
+/// Actions can also change it, in the order they're called (if one of the changes can't happen,
+/// none of them do):
+///  - `draw(n)`, `gain(combat, authority, trade)`, `opponent_discards(n)`
+///  - `scrap(id)`, scrap one of your cards
+///  - `destroy_base(id)`, destroy one of the opponent's bases (outposts first)
+///
+/// An action that uses `picked_card` has the player pick one of their cards first,
+/// one that uses `picked_base` has them pick one of the opponent's bases. Both are hand ids.
+///
+/// Scripts see the game as it was before they ran, and can't touch anything else:
+/// no files, no modules, no `eval`, and a limited number of operations.
+pub const SCRIPT_PREFIX: &str = "script ";
+
+const MAX_OPERATIONS: u64 = 10_000;
+/// the most cards (or goods) a single call can ask for
+const MAX_AMOUNT: u32 = 100;
+
+/// changes a script asks for, applied after it finishes
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Command {
+    Draw(u32),
+    Gain(Goods),
+    OpponentDiscards(u32),
+    Scrap(HandId),
+    DestroyBase(HandId),
+}
+
+/// what a script can know about the game
+struct ScriptView {
+    id: HandId,
+    authority: INT,
+    opponent_authority: INT,
+    goods: Goods,
+    hand_size: INT,
+    /// (hand id, is a base, factions) of every card the current player has in play
+    in_play: Vec<(HandId, bool, HashSet<Faction>)>,
+    factions: Factions,
+}
+
+impl ScriptView {
+    fn new(game: &GameState, id: &HandId) -> ScriptView {
+        let player = game.get_current_player();
+        ScriptView {
+            id: *id,
+            authority: game.authority_of(game.current_player) as INT,
+            opponent_authority: game.authority_of(game.get_target()) as INT,
+            goods: *player.get_goods(),
+            hand_size: player.unplayed_hand_card_ids().len() as INT,
+            in_play: player.effect_cards()
+                .filter(|(_, (_, status))| status.in_play)
+                .map(|(id, (card, status))| (*id, card.base.is_some(), status.factions(card)))
+                .collect(),
+            factions: game.card_library.get_factions().clone(),
+        }
+    }
+
+    fn count(&self, f: impl Fn(&(HandId, bool, HashSet<Faction>)) -> bool) -> INT {
+        self.in_play.iter().filter(|card| f(card)).count() as INT
+    }
+
+    fn faction(&self, code: &str) -> Result<Faction, Box<EvalAltResult>> {
+        self.factions.parse(code).map_err(|e| e.into())
+    }
+}
+
+/// which variable a script action needs picked, if any
+fn get_pick(code: &str) -> Result<Option<&'static str>, String> {
+    let uses = |name: &str| regex::Regex::new(&format!(r"\b{}\b", name)).unwrap().is_match(code);
+    match (uses("picked_card"), uses("picked_base")) {
+        (true, true) => Err("a script can only pick one thing".to_string()),
+        (true, false) => Ok(Some("picked_card")),
+        (false, true) => Ok(Some("picked_base")),
+        (false, false) => Ok(None)
+    }
+}
+
+fn get_pick_method(var: &str) -> ActionConfigMethod {
+    match var {
+        "picked_base" => ActionConfigMethod::PickBase(RelativePlayer::Current, RelativePlayer::Opponent, true),
+        _ => ActionConfigMethod::PickHandCard(RelativePlayer::Current, RelativePlayer::Current)
+    }
+}
+
+/// an engine that can't reach anything outside the game
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(16)
+        .set_max_expr_depths(32, 16)
+        .set_max_string_size(256)
+        .set_max_array_size(64)
+        .set_max_map_size(64)
+        .set_max_modules(0)
+        .disable_symbol("eval")
+        .on_print(|_| ())
+        .on_debug(|_, _, _| ());
+    engine
+}
+
+/// the engine for a script, with the queries and (if `commands` is given) the actions registered
+fn engine_for(view: Rc<ScriptView>, commands: Option<Rc<RefCell<Vec<Command>>>>) -> Engine {
+    let mut engine = sandboxed_engine();
+    let v = Rc::clone(&view);
+    engine.register_fn("authority", move || v.authority);
+    let v = Rc::clone(&view);
+    engine.register_fn("opponent_authority", move || v.opponent_authority);
+    let v = Rc::clone(&view);
+    engine.register_fn("trade", move || v.goods.trade as INT);
+    let v = Rc::clone(&view);
+    engine.register_fn("combat", move || v.goods.combat as INT);
+    let v = Rc::clone(&view);
+    engine.register_fn("hand_size", move || v.hand_size);
+    let v = Rc::clone(&view);
+    engine.register_fn("bases_in_play", move || v.count(|(_, base, _)| *base));
+    let v = Rc::clone(&view);
+    engine.register_fn("ships_in_play", move || v.count(|(_, base, _)| !*base));
+    let v = Rc::clone(&view);
+    engine.register_fn("cards_in_play", move |code: &str| -> Result<INT, Box<EvalAltResult>> {
+        let faction = v.faction(code)?;
+        Ok(v.count(|(_, _, factions)| factions.contains(&faction)))
+    });
+    let v = Rc::clone(&view);
+    engine.register_fn("has_ally", move |code: &str| -> Result<bool, Box<EvalAltResult>> {
+        let faction = v.faction(code)?;
+        Ok(v.count(|(id, _, factions)| *id != v.id && factions.contains(&faction)) > 0)
+    });
+
+    if let Some(commands) = commands {
+        fn amount(n: INT) -> Result<u32, Box<EvalAltResult>> {
+            match u32::try_from(n) {
+                Ok(n) if n <= MAX_AMOUNT => Ok(n),
+                _ => Err(format!("{} is not an amount between 0 and {}", n, MAX_AMOUNT).into())
+            }
+        }
+        fn id(n: INT) -> Result<HandId, Box<EvalAltResult>> {
+            HandId::try_from(n).map_err(|_| format!("{} is not a valid hand id", n).into())
+        }
+        let c = Rc::clone(&commands);
+        engine.register_fn("draw", move |n: INT| -> Result<(), Box<EvalAltResult>> {
+            c.borrow_mut().push(Command::Draw(amount(n)?));
+            Ok(())
+        });
+        let c = Rc::clone(&commands);
+        engine.register_fn("gain", move |combat: INT, authority: INT, trade: INT| -> Result<(), Box<EvalAltResult>> {
+            c.borrow_mut().push(Command::Gain(Goods {
+                combat: amount(combat)?,
+                authority: amount(authority)? as Authority,
+                trade: amount(trade)?,
+            }));
+            Ok(())
+        });
+        let c = Rc::clone(&commands);
+        engine.register_fn("opponent_discards", move |n: INT| -> Result<(), Box<EvalAltResult>> {
+            c.borrow_mut().push(Command::OpponentDiscards(amount(n)?));
+            Ok(())
+        });
+        let c = Rc::clone(&commands);
+        engine.register_fn("scrap", move |n: INT| -> Result<(), Box<EvalAltResult>> {
+            c.borrow_mut().push(Command::Scrap(id(n)?));
+            Ok(())
+        });
+        let c = Rc::clone(&commands);
+        engine.register_fn("destroy_base", move |n: INT| -> Result<(), Box<EvalAltResult>> {
+            c.borrow_mut().push(Command::DestroyBase(id(n)?));
+            Ok(())
+        });
+    }
+    engine
+}
+
+/// ex. "script draw(1)". None if it's not a script, or it doesn't compile
+pub fn get_script_action(name: &str) -> Option<(ActionMeta, ActionFunc)> {
+    let code = name.strip_prefix(SCRIPT_PREFIX)?.to_string();
+    let ast: AST = sandboxed_engine().compile(&code).ok()?;
+    let pick = get_pick(&code).ok()?;
+    Some(
+        (
+            ActionMeta {
+                description: format!("Script: {}", code.trim()),
+                config: pick.map(|var| Config {
+                    describe: Box::new(move |i| format!("{} = {}", var, i)),
+                    config_method: get_pick_method(var)
+                })
+            },
+            Box::new(move |game, id, cfg| {
+                let commands = Rc::new(RefCell::new(vec![]));
+                let engine = engine_for(Rc::new(ScriptView::new(game, id)), Some(Rc::clone(&commands)));
+                let mut scope = Scope::new();
+                if let Some(var) = pick {
+                    scope.push_constant(var, cfg as INT);
+                }
+                if let Err(e) = engine.run_ast_with_scope(&mut scope, &ast) {
+                    return Fail(format!("The script failed: {}", e));
+                }
+                let commands = commands.borrow().clone();
+                // all of the commands happen, or none of them do
+                let before = game.snapshot();
+                for command in commands {
+                    if let Err(e) = apply(game, command) {
+                        game.restore(before);
+                        return Fail(e);
+                    }
+                }
+                Succeed
+            })
+        )
+    )
+}
+
+/// ex. "script bases_in_play() >= 2". It's not met if the script fails or doesn't give a bool.
+/// None if it's not a script, or it doesn't compile
+pub fn get_script_condition(name: &str) -> Option<ConditionFunc> {
+    let code = name.strip_prefix(SCRIPT_PREFIX)?;
+    let ast: AST = sandboxed_engine().compile_expression(code).ok()?;
+    Some(Box::new(move |game, id| {
+        engine_for(Rc::new(ScriptView::new(game, id)), None)
+            .eval_ast_with_scope::<bool>(&mut Scope::new(), &ast)
+            .unwrap_or(false)
+    }))
+}
+
+fn apply(game: &mut GameState, command: Command) -> Result<(), String> {
+    match command {
+        Command::Draw(n) => {
+            for _ in 0..n {
+                game.get_current_player_mut().draw_into_hand();
+            }
+        }
+        Command::Gain(goods) => game.gain_goods(game.current_player, goods),
+        Command::OpponentDiscards(n) => {
+            for _ in 0..n {
+                game.get_current_opponent_mut().add_pending_discard();
+            }
+        }
+        Command::Scrap(id) => game.get_current_player_mut().force_plan_scrap(&id)?,
+        Command::DestroyBase(id) => {
+            // like "destroy target base", nothing happens if there's nothing to destroy
+            if !game.get_current_opponent().targetable_bases(true).is_empty() {
+                game.destroy_base(&RelativePlayer::Opponent, &id, true)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -10,14 +10,36 @@ mod tests {
     use yaml_rust::YamlLoader;
 
     use crate::game::components::card::Base;
-    use crate::game::components::card::Card;
-    use crate::game::components::faction::Faction;
+    use crate::game::components::card::{Card, CardFilter, CardKind};
+    use crate::game::components::{Authority, Coin, Combat};
+    use crate::game::components::faction::{Faction, FactionInfo, Factions};
     use crate::game::components::stack::Stack;
-    use crate::game::effects::assert_validate_card_effects;
-    use crate::game::{Goods, GameState, PlayerArea};
-    use crate::parse::{parse_card, parse_file, parse_goods};
+    use crate::game::effects::{ActionMeta, ActionFunc, ConditionFunc, assert_validate_card_effects, validate_card_effects, validate_mission, get_objective, get_action, get_condition, is_trash_cond, parse_acquire, get_good_action, ConfigSupplier, Config,
+                                ActionConfigMethod, encode_arrangement, decode_arrangement};
+    use crate::game::util::Failure;
+    use crate::game::{Goods, GameState, GameFormat, PlayerArea, Player, AcquireTo, MISSIONS_TO_WIN, TRADE_ROW_SIZE, RelativePlayer, GameEvent, Counter,
+                      TurnPhase, AttackTarget, CombatAction, AbstractPlayerAction, UserActionIntent, UserActionSupplier, HandId, Feedback, Zone};
+    use crate::game::components::scrap_heap::ScrapSource;
+    use crate::game::components::mission::Mission;
+    use crate::parse::{parse_card, parse_file, parse_goods, parse_missions_file};
     use crate::game::card_library::CardLibrary;
+    use crate::game::effect_registry::EffectRegistry;
     use std::mem;
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    /// a game with the core set and some optional sets, which `from_config` leaves out
+    fn game_with_sets(sets: &[&str]) -> GameState {
+        let names: Vec<_> = ["core"].iter().chain(sets.iter()).copied().collect();
+        GameState::new(Rc::new(CardLibrary::from_sets("config", &names).unwrap()))
+    }
+
+    /// a card from `test_homebrew.yaml`, which isn't in any set
+    fn test_card(cl: &CardLibrary, name: &str) -> Card {
+        parse_file("config/test_homebrew.yaml".to_owned(), cl.get_factions()).unwrap()
+            .into_iter().find(|card| card.name == name).unwrap()
+    }
 
     #[test]
     fn test_shuffle() {
@@ -29,6 +51,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stack_top() {
+        // the top is the end
+        let mut stack = Stack::new(vec![1, 2, 3, 4, 5]);
+        assert_eq!(stack.peek_top(2), vec![&5, &4]);
+        assert_eq!(stack.peek_top(9).len(), 5);
+        assert_eq!(stack.positions(|n| n % 2 == 1), vec![4, 2, 0]);
+        assert_eq!(stack.draw_top(2), vec![5, 4]);
+        stack.put_on_top(vec![4, 5]);
+        assert_eq!(stack.elements, vec![1, 2, 3, 5, 4]);
+        assert_eq!(stack.draw(), Some(4));
+        assert_eq!(stack.draw_top(9), vec![5, 3, 2, 1]);
+        assert_eq!(stack.len(), 0);
+    }
+
     #[test]
     fn test_yaml() {
         // yeah: don't test your libraries, but also idk how this works
@@ -66,16 +103,17 @@ card1:
     - b
         ");
         let yaml = &yaml.unwrap()[0];
-        let card = parse_card("card1", yaml["card1"].clone()).unwrap();
+        let card = parse_card("card1", yaml["card1"].clone(), &Factions::core()).unwrap();
         // println!("{:?}", card);
         assert_eq!(card, Card {
             cost: 1,
             name: "card1".to_owned(),
             base: None,
+            kind: CardKind::Deck,
             synergizes_with: {
                 let mut set = HashSet::new();
-                set.insert(Faction::Mech);
-                set.insert(Faction::Blob);
+                set.insert(Faction('m'));
+                set.insert(Faction('b'));
                 set
             },
             effects: HashSet::new(),
@@ -98,16 +136,17 @@ card2:
     - any: test
         ");
         let yaml = &yaml.unwrap()[0];
-        let card = parse_card("card2", yaml["card2"].clone()).unwrap();
+        let card = parse_card("card2", yaml["card2"].clone(), &Factions::core()).unwrap();
         // println!("{:?}", card);
         assert_eq!(card, Card {
             cost: 2,
             name: "card2".to_owned(),
             base: Some(Base::Outpost(4)),
+            kind: CardKind::Deck,
             synergizes_with: {
                 let mut set = HashSet::new();
-                set.insert(Faction::Star);
-                set.insert(Faction::Fed);
+                set.insert(Faction('s'));
+                set.insert(Faction('t'));
                 set
             },
             effects: {
@@ -121,17 +160,18 @@ card2:
 
     #[test]
     fn parse_multiple_cards() {
-        let cards = parse_file("config/test.yaml".to_owned()).unwrap();
+        let cards = parse_file("config/test.yaml".to_owned(), &Factions::core()).unwrap();
         assert_eq!(cards.len(), 2);
         assert_eq!(cards[0], Card {
             cost: 1,
             name: "card1".to_owned(),
             base: None,
+            kind: CardKind::Deck,
             effects: HashSet::new(),
             synergizes_with: {
                 let mut set = HashSet::new();
-                set.insert(Faction::Mech);
-                set.insert(Faction::Fed);
+                set.insert(Faction('m'));
+                set.insert(Faction('t'));
                 set
             },
         });
@@ -139,10 +179,11 @@ card2:
             cost: 2,
             name: "card2".to_owned(),
             base: Some(Base::Base(6)),
+            kind: CardKind::Deck,
             synergizes_with: {
                 let mut set = HashSet::new();
-                set.insert(Faction::Star);
-                set.insert(Faction::Blob);
+                set.insert(Faction('s'));
+                set.insert(Faction('b'));
                 set
             },
             effects: {
@@ -157,15 +198,86 @@ card2:
 
     #[test]
     fn validate_misc_cards () {
-        let cards = parse_file("config/misc_cards.yaml".to_owned()).unwrap();
+        let cards = parse_file("config/misc_cards.yaml".to_owned(), &Factions::core()).unwrap();
+        for card in cards.iter() {
+            assert_validate_card_effects(card);
+        }
+    }
+
+    #[test]
+    fn validate_gambits () {
+        let cards = parse_file("config/gambits.yaml".to_owned(), &Factions::core()).unwrap();
+        for card in cards.iter() {
+            assert_validate_card_effects(card);
+        }
+    }
+
+    #[test]
+    fn validate_heroes () {
+        let cards = parse_file("config/heroes.yaml".to_owned(), &Factions::core()).unwrap();
+        for card in cards.iter() {
+            assert!(card.is_hero());
+            assert_validate_card_effects(card);
+        }
+        let yaml = YamlLoader::load_from_str("bad: {cost: 1, hero: true, base: true, defense: 1, outpost: false}");
+        assert!(parse_card("bad", yaml.unwrap()[0]["bad"].clone(), &Factions::core()).is_err());
+    }
+
+    #[test]
+    fn validate_events () {
+        let cards = parse_file("config/events.yaml".to_owned(), &Factions::core()).unwrap();
+        for card in cards.iter() {
+            assert!(card.is_event());
+            assert_validate_card_effects(card);
+        }
+        let yaml = YamlLoader::load_from_str("\
+bad:
+  cost: 0
+  event: true
+  base: false
+  effects:
+    - event: destroy target base
+        ");
+        let card = parse_card("bad", yaml.unwrap()[0]["bad"].clone(), &Factions::core()).unwrap();
+        assert!(validate_card_effects(&card).is_some(), "nobody can pick the base");
+        assert!(get_action(&"each player acquire ship".to_string()).is_none());
+    }
+
+    #[test]
+    fn validate_missions () {
+        let missions = parse_missions_file("config/missions.yaml".to_owned()).unwrap();
+        for mission in missions.iter() {
+            assert_eq!(validate_mission(mission), None, "{}", mission.name);
+        }
+        // which factions exist is up to the card library, an unknown one is never met
+        let game = GameState::from_config("config").unwrap();
+        assert!(!get_objective("0 x cards in play").unwrap()(&game, Player(0)));
+        assert!(get_objective("0 b cards in play").unwrap()(&game, Player(0)));
+        assert!(get_objective("all the bases").is_none());
+    }
+
+    #[test]
+    fn validate_colony_wars () {
+        let cards = parse_file("config/colony_wars.yaml".to_owned(), &Factions::core()).unwrap();
         for card in cards.iter() {
             assert_validate_card_effects(card);
         }
+        let card = |effects: &[(&str, &str)]| Card {
+            cost: 0,
+            name: "Mock".to_string(),
+            base: None,
+            kind: CardKind::Deck,
+            synergizes_with: Default::default(),
+            effects: effects.iter().map(|(c, a)| (c.to_string(), a.to_string())).collect()
+        };
+        assert!(validate_card_effects(&card(&[("free", "to hand")])).is_some());
+        assert!(validate_card_effects(&card(&[("acquired", "destroy target base")])).is_some());
+        assert!(validate_card_effects(&card(&[("acquired-syn-b", "to top")])).is_none());
     }
 
     #[test]
     fn validate_trade_cards () {
-        let cards = parse_file("config/trade_cards.yaml".to_owned()).unwrap();
+        let cards = parse_file("config/trade_cards.yaml".to_owned(), &Factions::core()).unwrap();
         for card in cards.iter() {
             assert_validate_card_effects(card);
         }
@@ -196,6 +308,7 @@ card2:
             cost: 1,
             name: "Scout Mock".to_string(),
             base: None,
+            kind: CardKind::Deck,
             synergizes_with: Default::default(),
             effects: Default::default()
         },
@@ -203,9 +316,10 @@ card2:
             cost: 1,
             name: "Viper Mock".to_string(),
             base: None,
+            kind: CardKind::Deck,
             synergizes_with: Default::default(),
             effects: Default::default()
-        }, false);
+        }, 5);
         player.draw_hand(5);
         let cfg = 0b111 as u32; // pick the first three
         let first_three = {
@@ -222,6 +336,1176 @@ card2:
         assert_eq!(cards, first_three);
     }
 
+    #[test]
+    fn test_scrap_heap () {
+        let mut game = GameState::from_config("config").unwrap();
+        let explorer = (*game.card_library.get_explorer().unwrap()).clone();
+        let needle = (*game.card_library.get_card_by_name("stealth_needle").unwrap()).clone();
+        let explorers = game.explorers;
+        game.scrap_card(explorer, Player(0), ScrapSource::Hand);
+        assert_eq!(game.explorers, explorers + 1);
+        assert!(game.get_scrap_heap().is_empty());
+
+        game.scrap_card(needle, Player(1), ScrapSource::TradeRow);
+        assert_eq!(game.get_scrap_heap().len(), 1);
+        assert_eq!(game.get_scrap_heap().scrapped_by(Player(0)).count(), 0);
+        let record = game.get_scrap_heap().scrapped_by(Player(1)).next().unwrap();
+        assert_eq!(record.card.name, "stealth_needle");
+        assert_eq!(record.from, ScrapSource::TradeRow);
+    }
+
+    #[test]
+    fn test_copy_ship () {
+        let mut game = GameState::from_config("config").unwrap();
+        let needle = (*game.card_library.get_card_by_name("stealth_needle").unwrap()).clone();
+        let pod = (*game.card_library.get_card_by_name("battle_pod").unwrap()).clone();
+        let player = game.get_current_player_mut();
+        let hand_count = player.get_all_hand_card_ids().len() + 2;
+        let needle_id = player.give_card_to_hand(needle);
+        let pod_id = player.give_card_to_hand(pod);
+        let (_, mut copy) = get_action(&"copy ship".to_string()).unwrap();
+
+        // the pod hasn't been played yet
+        assert!(matches!(copy(&mut game, &needle_id, pod_id), Failure::Fail(_)));
+        assert!(matches!(copy(&mut game, &needle_id, needle_id), Failure::Fail(_)));
+
+        let effect = ("free".to_string(), "G4.0.0".to_string());
+        game.get_current_player_mut().use_effect(&pod_id, &effect).unwrap();
+        assert!(matches!(copy(&mut game, &needle_id, pod_id), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand_count,
+                   "copying should not create any cards");
+
+        let (card, card_status) = game.get_current_player().get_card_in_hand(&needle_id).unwrap();
+        assert!(card_status.factions(card).contains(&Faction('b')));
+        assert!(card_status.unused_effects(card).contains(&effect));
+        assert!(!card.effects.contains(&effect), "the card itself must not change");
+    }
+
+    #[test]
+    fn test_faction_granting () {
+        let mut game = GameState::from_config("config").unwrap();
+        let merc = (*game.card_library.get_card_by_name("merc_cruiser").unwrap()).clone();
+        let screecher = (*game.card_library.get_card_by_name("battle_screecher").unwrap()).clone();
+        let player = game.get_current_player_mut();
+        let merc_id = player.give_card_to_hand(merc);
+        let screecher_id = player.give_card_to_hand(screecher);
+        let mut ally = get_condition("syn-b".to_string()).unwrap();
+        assert!(!ally(&game, &screecher_id));
+
+        let (meta, mut add_faction) = get_action(&"add faction".to_string()).unwrap();
+        assert!(meta.config.is_some());
+        let blob = game.card_library.get_factions().all().iter().position(|f| *f == Faction('b')).unwrap() as u32;
+        assert!(matches!(add_faction(&mut game, &merc_id, blob), Failure::Succeed));
+        game.get_current_player_mut()
+            .use_effect(&merc_id, &("free".to_string(), "add faction".to_string())).unwrap();
+        assert!(ally(&game, &screecher_id));
+        // a card is not its own ally
+        assert!(!ally(&game, &merc_id));
+
+        let (meta, mut set_faction) = get_action(&"set faction s".to_string()).unwrap();
+        assert!(meta.config.is_none());
+        assert!(matches!(set_faction(&mut game, &merc_id, 0), Failure::Succeed));
+        let (card, card_status) = game.get_current_player().get_card_in_hand(&merc_id).unwrap();
+        assert_eq!(card_status.factions(card), [Faction('s')].iter().cloned().collect());
+        assert!(!ally(&game, &screecher_id));
+    }
+
+    #[test]
+    fn test_acquire_from_trade_row () {
+        let (filter, to) = parse_acquire("acquire ship cost 4 faction b to top").unwrap();
+        assert_eq!(filter, CardFilter {
+            ships_only: true,
+            bases_only: false,
+            max_cost: Some(4),
+            faction: Some(Faction('b'))
+        });
+        assert_eq!(to, AcquireTo::TopOfDeck);
+        assert!(parse_acquire("acquire ship cost").is_none());
+        assert!(parse_acquire("acquire something").is_none());
+
+        let mut game = GameState::from_config("config").unwrap();
+        let cl = Rc::clone(&game.card_library);
+        let id = |name: &str| *cl.get_card_id(&name.to_string()).unwrap();
+        let row = vec![id("port_of_call"), id("blob_carrier"), id("battle_pod")];
+        let refill = vec![id("merc_cruiser"), id("merc_cruiser"), id("merc_cruiser")];
+        game.trade_row = Stack::new(row);
+        game.trade_row_stack = Stack::new(refill);
+
+        let (_, mut acquire) = get_action(&"acquire ship cost 4 to top".to_string()).unwrap();
+        assert_eq!(game.trade_row_choices(&filter), vec![2]);
+        assert!(matches!(acquire(&mut game, &0, 0), Failure::Fail(_)));
+        assert!(matches!(acquire(&mut game, &0, 1), Failure::Fail(_)));
+        assert!(matches!(acquire(&mut game, &0, 2), Failure::Succeed));
+        assert_eq!(game.trade_row.len(), TRADE_ROW_SIZE);
+        assert_eq!(game.trade_row.peek(0), Some(&id("port_of_call")));
+
+        let player = game.get_current_player_mut();
+        let before = player.get_all_hand_card_ids();
+        player.draw_into_hand();
+        let drawn = player.get_all_hand_card_ids().difference(&before).next().cloned().unwrap();
+        assert_eq!(player.get_card_in_hand(&drawn).unwrap().0.name, "battle_pod");
+    }
+
+    #[test]
+    fn test_destroy_base () {
+        let mut game = GameState::from_config("config").unwrap();
+        let outpost = (*game.card_library.get_card_by_name("port_of_call").unwrap()).clone();
+        let base = Card {
+            cost: 3,
+            name: "Base Mock".to_string(),
+            base: Some(Base::Base(5)),
+            kind: CardKind::Deck,
+            synergizes_with: Default::default(),
+            effects: Default::default()
+        };
+        let opponent = game.get_current_opponent_mut();
+        let outpost_id = opponent.give_card_to_hand(outpost);
+        let base_id = opponent.give_card_to_hand(base);
+        opponent.get_card_in_hand_mut(&outpost_id).unwrap().1.reveal();
+        opponent.get_card_in_hand_mut(&base_id).unwrap().1.reveal();
+        assert_eq!(opponent.targetable_bases(true), vec![outpost_id]);
+
+        assert!(game.destroy_base(&RelativePlayer::Opponent, &base_id, true).is_err());
+        assert!(game.take_events().is_empty());
+        let (_, mut destroy) = get_action(&"destroy target base".to_string()).unwrap();
+        assert!(matches!(destroy(&mut game, &0, base_id), Failure::Succeed));
+        assert!(game.get_current_opponent().get_card_in_hand(&base_id).is_none());
+        match game.take_events().as_slice() {
+            [GameEvent::BaseDestroyed { owner, by, base }] => {
+                assert_eq!(*owner, Player(1));
+                assert_eq!(*by, Player(0));
+                assert_eq!(base.name, "Base Mock");
+            }
+            events => panic!("expected one destroyed base, got {:?}", events)
+        }
+        assert_eq!(game.get_current_opponent().targetable_bases(true), vec![outpost_id]);
+    }
+
+    #[test]
+    fn test_turn_phases () {
+        let mut game = GameState::from_config("config").unwrap();
+        let client = ScriptedClient::default();
+        assert_eq!(game.get_phase(), TurnPhase::StartOfTurn);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Main);
+
+        let outpost = (*game.card_library.get_card_by_name("port_of_call").unwrap()).clone();
+        let opponent = game.get_current_opponent_mut();
+        let outpost_id = opponent.give_card_to_hand(outpost);
+        opponent.get_card_in_hand_mut(&outpost_id).unwrap().1.reveal();
+        let mut combat = get_good_action(Goods { trade: 0, authority: 0, combat: 8 });
+        assert!(matches!(combat(&mut game, &0, 0), Failure::Succeed));
+        let (_, mut discard) = get_action(&"discard".to_string()).unwrap();
+        assert!(matches!(discard(&mut game, &0, 0), Failure::Succeed));
+        assert_eq!(game.get_current_opponent().get_goods().authority, 50, "combat waits for the combat phase");
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.confirms.borrow_mut().push_back(true);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Combat);
+        assert!(game.attack(AttackTarget::Authority(Player(1))).is_err(), "the outpost is in the way");
+        game.attack(AttackTarget::Base(Player(1), outpost_id)).unwrap();
+        game.attack(AttackTarget::Authority(Player(1))).unwrap();
+        assert_eq!(game.get_current_opponent().get_goods().authority, 48);
+        assert_eq!(game.get_current_player().get_goods().combat, 0);
+
+        // no combat left, so the combat phase ends by itself
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Discard);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Draw);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::StartOfTurn);
+        assert_eq!(game.get_current_player_id(), Player(1));
+
+        let player = game.get_current_player();
+        assert_eq!(player.get_pending_discards(), 1);
+        let hand_size = player.get_all_hand_card_ids().len();
+        let to_discard = *player.unplayed_hand_card_ids().iter().next().unwrap();
+        client.configs.borrow_mut().push_back(to_discard);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Main);
+        assert_eq!(game.get_current_player().get_pending_discards(), 0);
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand_size - 1);
+    }
+
+    #[test]
+    fn test_triggers () {
+        let mut game = GameState::from_config("config").unwrap();
+        let client = ScriptedClient::default();
+        let effect = |c: &str, a: &str| (c.to_string(), a.to_string());
+        let base = Card {
+            cost: 3,
+            name: "Trigger Base Mock".to_string(),
+            base: Some(Base::Base(5)),
+            kind: CardKind::Deck,
+            synergizes_with: Default::default(),
+            // a trigger that fails (or doesn't exist) doesn't hold up the rest of the turn
+            effects: [effect("start", "G0.3.0"), effect("start", "script draw(-1)"), effect("start", "no such effect")]
+                .iter().cloned().collect()
+        };
+        let ship = Card {
+            cost: 3,
+            name: "Trigger Ship Mock".to_string(),
+            base: None,
+            kind: CardKind::Deck,
+            synergizes_with: Default::default(),
+            effects: [effect("free", "G0.0.1"), effect("end-of-turn", "G0.2.0")].iter().cloned().collect()
+        };
+        let mut cond = get_condition("start".to_string()).unwrap();
+        let player = game.get_current_player_mut();
+        let base_id = player.give_card_to_hand(base);
+        player.get_card_in_hand_mut(&base_id).unwrap().1.reveal();
+        assert!(!cond(&game, &base_id), "triggers cannot be picked by the player");
+
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Main);
+        assert_eq!(game.get_current_player().get_goods().authority, 53);
+        let (card, card_status) = game.get_current_player().get_card_in_hand(&base_id).unwrap();
+        assert!(card_status.all_effects_used(card));
+
+        let ship_id = game.get_current_player_mut().give_card_to_hand(ship);
+        client.effects.borrow_mut().push_back(UserActionIntent::Continue((ship_id, effect("free", "G0.0.1"))));
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.confirms.borrow_mut().push_back(true);
+        game.advance(&client).unwrap();
+        game.advance(&client).unwrap();
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Discard);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().authority, 55);
+    }
+
+    #[test]
+    fn test_play_all_free () {
+        let mut game = GameState::from_config("config").unwrap();
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        let needle = (*game.card_library.get_card_by_name("stealth_needle").unwrap()).clone();
+        let needle_id = game.get_current_player_mut().give_card_to_hand(needle);
+        // every card in the starting hand is a scout or a viper
+        let hand: Vec<_> = game.get_current_player().get_all_hand_card_ids().into_iter()
+            .filter(|id| *id != needle_id)
+            .collect();
+        assert_eq!(game.choice_free_effects().len(), hand.len());
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::PlayAllFree);
+        game.advance(&client).unwrap();
+        assert!(game.choice_free_effects().is_empty());
+        let goods = game.get_current_player().get_goods();
+        assert_eq!(goods.trade + goods.combat, hand.len() as Coin);
+        // copying a ship needs a choice, so it was left alone
+        let (card, card_status) = game.get_current_player().get_card_in_hand(&needle_id).unwrap();
+        assert!(!card_status.all_effects_used(card));
+
+        // an effect that fails is skipped, and the rest are still used
+        let mut broken = (*game.card_library.get_card_by_name("battle_pod").unwrap()).clone();
+        broken.effects = [("free".to_string(), "script draw(-1)".to_string()),
+            ("free".to_string(), "G0.0.1".to_string())].iter().cloned().collect();
+        let broken_id = game.get_current_player_mut().give_card_to_hand(broken);
+        let trade = game.get_current_player().get_goods().trade;
+        assert_eq!(game.play_all_free(&client), 1);
+        assert_eq!(game.get_current_player().get_goods().trade, trade + 1);
+        let (card, card_status) = game.get_current_player().get_card_in_hand(&broken_id).unwrap();
+        assert!(card_status.all_effects_used(card));
+        assert!(game.choice_free_effects().is_empty());
+    }
+
+    #[test]
+    fn test_wasted_resources () {
+        let mut game = GameState::from_config("config").unwrap();
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        let hand_size = game.get_current_player().get_all_hand_card_ids().len();
+        let wasted = game.wasted_resources();
+        assert_eq!(wasted.unplayed_cards.len(), hand_size);
+        assert!(wasted.unused_effects.is_empty());
+
+        let pod = (*game.card_library.get_card_by_name("battle_pod").unwrap()).clone();
+        let pod_id = game.get_current_player_mut().give_card_to_hand(pod);
+        let effect = |c: &str, a: &str| (c.to_string(), a.to_string());
+        client.effects.borrow_mut().push_back(UserActionIntent::Continue((pod_id, effect("free", "G4.0.0"))));
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::PlayAllFree);
+        game.advance(&client).unwrap();
+        game.advance(&client).unwrap();
+        let wasted = game.wasted_resources();
+        assert!(wasted.unplayed_cards.is_empty());
+        // the ally effect can't be used, so it isn't wasted
+        assert_eq!(wasted.unused_effects, vec![("battle_pod".to_string(), effect("free", "scrap trade row"))]);
+        assert!(wasted.trade > 0);
+        assert_eq!(wasted.combat, 0, "combat is spent in the combat phase");
+
+        // refusing to waste them keeps the turn going
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.confirms.borrow_mut().push_back(false);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Main);
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.confirms.borrow_mut().push_back(true);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Combat);
+        assert!(game.wasted_resources().combat >= 4);
+    }
+
+    #[test]
+    fn test_large_goods () {
+        let mut game = GameState::from_config("config").unwrap();
+        let mut big_turn = get_action(&"G200.300.400".to_string()).unwrap().1;
+        for _ in 0..3 {
+            assert!(matches!(big_turn(&mut game, &0, 0), Failure::Succeed));
+        }
+        let goods = *game.get_current_player().get_goods();
+        assert_eq!(goods, Goods { combat: 600, authority: 950, trade: 1200 });
+
+        game.attack(AttackTarget::Authority(Player(1))).unwrap();
+        assert_eq!(game.get_current_opponent().get_goods().authority, -550);
+        assert_eq!(game.winners(), Some(vec![Player(0)]));
+
+        // too big to be goods at all
+        assert!(parse_goods("G99999999999.0.0").is_none());
+        let most = Goods { combat: Combat::MAX, authority: Authority::MAX, trade: Coin::MAX };
+        assert_eq!(most + Goods { combat: 1, authority: 1, trade: 1 }, most);
+    }
+
+    #[test]
+    fn test_undo () {
+        let mut game = GameState::from_config("config").unwrap();
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        assert!(!game.can_undo());
+        let pod = (*game.card_library.get_card_by_name("battle_pod").unwrap()).clone();
+        let pod_id = game.get_current_player_mut().give_card_to_hand(pod);
+        let effect = ("free".to_string(), "G4.0.0".to_string());
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(UserActionIntent::Continue((pod_id, effect.clone())));
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().combat, 4);
+        // steps that don't change anything aren't undone
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(UserActionIntent::Cancel);
+        game.advance(&client).unwrap();
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::Undo);
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().combat, 0);
+        let (card, card_status) = game.get_current_player().get_card_in_hand(&pod_id).unwrap();
+        assert!(card_status.unused_effects(card).contains(&effect));
+        assert!(!game.can_undo());
+        assert!(game.undo().is_err());
+
+        // going into the combat phase can be taken back
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(UserActionIntent::Continue((pod_id, effect)));
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.confirms.borrow_mut().push_back(true);
+        game.advance(&client).unwrap();
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Combat);
+        client.combat.borrow_mut().push_back(UserActionIntent::Continue(CombatAction::Undo));
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Main);
+        assert_eq!(game.get_current_player().get_goods().combat, 4);
+        assert!(game.can_undo());
+
+        // drawing a card can't be taken back, and neither can anything before it
+        let drawing = Card {
+            cost: 1,
+            name: "Draw Mock".to_string(),
+            base: None,
+            kind: CardKind::Deck,
+            synergizes_with: Default::default(),
+            effects: [("free".to_string(), "draw".to_string())].iter().cloned().collect()
+        };
+        let drawing_id = game.get_current_player_mut().give_card_to_hand(drawing);
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(
+            UserActionIntent::Continue((drawing_id, ("free".to_string(), "draw".to_string()))));
+        game.advance(&client).unwrap();
+        assert!(!game.can_undo());
+    }
+
+    /// plays back scripted choices, panicking if it runs out
+    #[derive(Default)]
+    struct ScriptedClient {
+        actions: RefCell<VecDeque<AbstractPlayerAction>>,
+        effects: RefCell<VecDeque<UserActionIntent<(HandId, (String, String))>>>,
+        combat: RefCell<VecDeque<UserActionIntent<CombatAction>>>,
+        configs: RefCell<VecDeque<u32>>,
+        confirms: RefCell<VecDeque<bool>>,
+        missions: RefCell<VecDeque<UserActionIntent<usize>>>,
+    }
+
+    impl UserActionSupplier for ScriptedClient {
+        fn choose_abstract_action(&self, _: &GameState) -> AbstractPlayerAction {
+            self.actions.borrow_mut().pop_front().expect("no more scripted actions")
+        }
+        fn select_effect(&self, _: &GameState) -> UserActionIntent<(HandId, (String, String))> {
+            self.effects.borrow_mut().pop_front().expect("no more scripted effects")
+        }
+        fn select_trade_row_card(&self, _: &GameState) -> UserActionIntent<HandId> {
+            UserActionIntent::Cancel
+        }
+        fn select_mission(&self, _: &GameState) -> UserActionIntent<usize> {
+            self.missions.borrow_mut().pop_front().expect("no more scripted missions")
+        }
+        fn select_opponent(&self, _: &GameState) -> UserActionIntent<Player> {
+            UserActionIntent::Cancel
+        }
+        fn select_combat_action(&self, _: &GameState) -> UserActionIntent<CombatAction> {
+            self.combat.borrow_mut().pop_front().expect("no more scripted combat actions")
+        }
+        fn on_feedback(&self, _: Feedback) {}
+        fn confirm(&self, _: &GameState, _: &str) -> bool {
+            self.confirms.borrow_mut().pop_front().expect("no more scripted confirmations")
+        }
+        fn on_event(&self, _: &GameEvent) {}
+    }
+
+    impl ConfigSupplier for ScriptedClient {
+        fn get_config(&self, _: &GameState, _: &Config) -> u32 {
+            self.configs.borrow_mut().pop_front().expect("no more scripted configs")
+        }
+    }
+
+    #[test]
+    fn test_multiplayer () {
+        let cl = Rc::new(CardLibrary::from_config("config").unwrap());
+        assert!(GameState::with_players(Rc::clone(&cl), 2, GameFormat::Hunter).is_err());
+        let game = GameState::with_players(Rc::clone(&cl), 4, GameFormat::FreeForAll).unwrap();
+        let hand_sizes: Vec<_> = (0..4)
+            .map(|i| game.get_player(Player(i)).get_all_hand_card_ids().len())
+            .collect();
+        assert_eq!(hand_sizes, vec![3, 4, 5, 5]);
+        assert_eq!(game.targetable_opponents(), vec![Player(1), Player(2), Player(3)]);
+
+        // in hunter, only the next player's authority can be attacked,
+        // but the bases on either side can
+        let mut game = GameState::with_players(Rc::clone(&cl), 4, GameFormat::Hunter).unwrap();
+        assert_eq!(game.attackable_authorities(), vec![Player(1)]);
+        assert_eq!(game.attackable_base_owners(), vec![Player(1), Player(3)]);
+        assert!(game.set_target(Player(2)).is_err());
+        game.set_target(Player(3)).unwrap();
+        let (_, mut discard) = get_action(&"discard".to_string()).unwrap();
+        assert!(matches!(discard(&mut game, &0, 0), Failure::Succeed));
+        assert_eq!(game.get_player(Player(3)).get_pending_discards(), 1);
+        assert_eq!(game.get_player(Player(1)).get_pending_discards(), 0);
+
+        let mut combat = get_good_action(Goods { trade: 0, authority: 0, combat: 60 });
+        assert!(matches!(combat(&mut game, &0, 0), Failure::Succeed));
+        assert!(game.attack(AttackTarget::Authority(Player(3))).is_err());
+        game.attack(AttackTarget::Authority(Player(1))).unwrap();
+        assert!(game.take_events().iter().any(|e| matches!(e,
+            GameEvent::PlayerEliminated { player: Player(1), by: Player(0) })));
+        // eliminated players are skipped
+        assert_eq!(game.attackable_authorities(), vec![Player(2)]);
+        assert_eq!(game.living_players(), vec![Player(0), Player(2), Player(3)]);
+        assert_eq!(game.winners(), None);
+
+        // in raids, the raiders can only attack the boss
+        let mut game = GameState::with_players(cl, 3, GameFormat::Raid).unwrap();
+        assert_eq!(game.get_player(Player(0)).get_goods().authority, 100);
+        assert_eq!(game.attackable_authorities(), vec![Player(1), Player(2)]);
+        let client = ScriptedClient::default();
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.confirms.borrow_mut().push_back(true);
+        while game.get_current_player_id() == Player(0) {
+            game.advance(&client).unwrap();
+        }
+        assert_eq!(game.get_current_player_id(), Player(1));
+        assert_eq!(game.attackable_authorities(), vec![Player(0)]);
+        assert_eq!(game.get_target(), Player(0));
+    }
+
+    #[test]
+    fn test_teams () {
+        let cl = Rc::new(CardLibrary::from_config("config").unwrap());
+        assert!(GameState::with_players(Rc::clone(&cl), 3, GameFormat::Teams).is_err());
+        let mut game = GameState::with_players(cl, 4, GameFormat::Teams).unwrap();
+        assert_eq!(game.authority_of(Player(0)), 75);
+        assert_eq!(game.authority_of(Player(1)), 75);
+        assert_eq!(game.attackable_authorities(), vec![Player(2), Player(3)]);
+        assert_eq!(game.get_target(), Player(2));
+
+        // authority is shared, so hitting either player hurts the whole team
+        let mut combat = get_good_action(Goods { trade: 0, authority: 0, combat: 30 });
+        assert!(matches!(combat(&mut game, &0, 0), Failure::Succeed));
+        game.attack(AttackTarget::Authority(Player(3))).unwrap();
+        assert_eq!(game.authority_of(Player(2)), 45);
+        // and both of them see the team's authority
+        for i in 2..4 {
+            assert_eq!(game.get_player(Player(i)).get_goods().authority, 45);
+        }
+        let mut heal = get_good_action(Goods { trade: 0, authority: 5, combat: 0 });
+        assert!(matches!(heal(&mut game, &0, 0), Failure::Succeed));
+        assert_eq!(game.get_player(Player(1)).get_goods().authority, 80);
+        assert!(matches!(combat(&mut game, &0, 0), Failure::Succeed));
+        assert!(matches!(combat(&mut game, &0, 0), Failure::Succeed));
+        game.attack(AttackTarget::Authority(Player(2))).unwrap();
+        assert!(game.is_eliminated(Player(3)));
+        assert_eq!(game.winners(), Some(vec![Player(0), Player(1)]));
+
+        // teammates play each phase of their team's turn one after the other
+        let cl = Rc::new(CardLibrary::from_config("config").unwrap());
+        let mut game = GameState::with_players(cl, 4, GameFormat::Teams).unwrap();
+        let client = ScriptedClient::default();
+        client.actions.borrow_mut().extend(vec![AbstractPlayerAction::EndTurn, AbstractPlayerAction::EndTurn]);
+        client.confirms.borrow_mut().extend(vec![true; 2]);
+        let mut turns = vec![(game.get_current_player_id(), game.get_phase())];
+        while game.get_current_player_id() != Player(3) {
+            game.advance(&client).unwrap();
+            turns.push((game.get_current_player_id(), game.get_phase()));
+        }
+        let (p0, p1, p2, p3) = (Player(0), Player(1), Player(2), Player(3));
+        assert_eq!(turns, vec![
+            (p0, TurnPhase::StartOfTurn), (p1, TurnPhase::StartOfTurn),
+            (p0, TurnPhase::Main), (p1, TurnPhase::Main),
+            (p0, TurnPhase::Combat), (p1, TurnPhase::Combat),
+            (p0, TurnPhase::Discard), (p1, TurnPhase::Discard),
+            (p0, TurnPhase::Draw), (p1, TurnPhase::Draw),
+            (p2, TurnPhase::StartOfTurn), (p3, TurnPhase::StartOfTurn),
+        ]);
+    }
+
+    #[test]
+    fn test_gambits () {
+        let mut game = game_with_sets(&["gambits"]);
+        assert!(game.deal_gambits(100).is_err());
+        game.deal_gambits(2).unwrap();
+        for i in 0..2 {
+            let player = game.get_player(Player(i));
+            let gambits = player.get_gambit_ids();
+            assert_eq!(gambits.len(), 2);
+            assert!(gambits.iter().all(|id| !player.get_all_hand_card_ids().contains(id)));
+        }
+
+        let mut game = game_with_sets(&["gambits"]);
+        let cl = Rc::clone(&game.card_library);
+        let player = game.get_current_player_mut();
+        let fleet = player.add_gambit((*cl.get_card_by_name("frontier_fleet").unwrap()).clone());
+        let rise = player.add_gambit((*cl.get_card_by_name("rise_to_power").unwrap()).clone());
+        assert_eq!(player.scrappable_card_ids(), vec![rise]);
+
+        // passive bonuses come from start of turn triggers
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_phase(), TurnPhase::Main);
+        assert_eq!(game.get_current_player().get_goods().combat, 1);
+
+        // gambits are scrapped like any other card with a trash effect
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::TrashCard);
+        client.configs.borrow_mut().push_back(rise);
+        game.advance(&client).unwrap();
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(
+            UserActionIntent::Continue((rise, ("trash".to_string(), "G0.8.0".to_string()))));
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().authority, 58);
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.combat.borrow_mut().push_back(UserActionIntent::Cancel);
+        client.confirms.borrow_mut().extend(vec![true, true]);
+        while game.get_current_player_id() == Player(0) {
+            game.advance(&client).unwrap();
+        }
+        let player = game.get_player(Player(0));
+        assert_eq!(player.get_gambit_ids(), vec![fleet]);
+        assert_eq!(game.get_scrap_heap().len(), 1);
+    }
+
+    #[test]
+    fn test_heroes () {
+        let mut game = game_with_sets(&["heroes"]);
+        let cl = Rc::clone(&game.card_library);
+        let id = |name: &str| *cl.get_card_id(&name.to_string()).unwrap();
+        game.trade_row = Stack::new(vec![id("ram_pilot"), id("battle_pod")]);
+        game.trade_row_stack = Stack::new(vec![]);
+
+        // buying a hero puts it into play
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        assert!(matches!(get_good_action(Goods { trade: 2, authority: 0, combat: 0 })(&mut game, &0, 0), Failure::Succeed));
+        game.acquire_from_trade_row(0, AcquireTo::Discard).unwrap();
+        let player = game.get_current_player();
+        let heroes = player.get_hero_ids();
+        assert_eq!(heroes.len(), 1);
+        let pilot = heroes[0];
+        assert!(!player.get_all_hand_card_ids().contains(&pilot));
+
+        // it's an ally of blob cards until it's scrapped
+        let pod = (*cl.get_card_by_name("battle_pod").unwrap()).clone();
+        let pod_id = game.get_current_player_mut().give_card_to_hand(pod);
+        let mut blob_ally = get_condition("syn-b".to_string()).unwrap();
+        assert!(blob_ally(&game, &pod_id));
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::TrashCard);
+        client.configs.borrow_mut().push_back(pilot);
+        game.advance(&client).unwrap();
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(
+            UserActionIntent::Continue((pilot, ("trash".to_string(), "G4.0.0".to_string()))));
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().combat, 4);
+
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::EndTurn);
+        client.combat.borrow_mut().push_back(UserActionIntent::Cancel);
+        client.confirms.borrow_mut().extend(vec![true, true]);
+        while game.get_current_player_id() == Player(0) {
+            game.advance(&client).unwrap();
+        }
+        assert!(game.get_player(Player(0)).get_hero_ids().is_empty());
+        assert_eq!(game.get_scrap_heap().iter().next().unwrap().card.name, "ram_pilot");
+    }
+
+    #[test]
+    fn test_events () {
+        let mut game = game_with_sets(&["events"]);
+        assert!(game.trade_row.iter().all(|id| !game.card_library.as_card(id).is_event()),
+                "events aren't dealt into the first trade row");
+        let cl = Rc::clone(&game.card_library);
+        let id = |name: &str| *cl.get_card_id(&name.to_string()).unwrap();
+        game.trade_row = Stack::new(vec![id("battle_pod")]);
+        // the top of the stack is drawn first
+        game.trade_row_stack = Stack::new(vec![id("blob_carrier"), id("peace_summit"), id("supply_drop")]);
+        let hand_sizes: Vec<_> = (0..2)
+            .map(|i| game.get_player(Player(i)).get_all_hand_card_ids().len())
+            .collect();
+
+        game.acquire_from_trade_row(0, AcquireTo::Discard).unwrap();
+        // both events were resolved and removed, and the carrier took the empty spot
+        assert_eq!(game.trade_row.elements, vec![id("blob_carrier")]);
+        assert_eq!(game.trade_row_stack.len(), 0);
+        for (i, hand_size) in hand_sizes.into_iter().enumerate() {
+            let player = game.get_player(Player(i));
+            assert_eq!(player.get_goods().authority, 55);
+            assert_eq!(player.get_all_hand_card_ids().len(), hand_size + 1);
+        }
+        assert_eq!(game.get_current_player_id(), Player(0), "everyone took part, then the turn went back");
+        let resolved: Vec<_> = game.take_events().into_iter().filter_map(|e| match e {
+            GameEvent::EventResolved { card, skipped } => {
+                assert!(skipped.is_empty());
+                Some(card.name)
+            }
+            _ => None
+        }).collect();
+        assert_eq!(resolved, vec!["supply_drop".to_string(), "peace_summit".to_string()]);
+
+        // an effect that can't happen is skipped and reported with the event
+        let card = |name: &str| (*cl.get_card_by_name(name).unwrap()).clone();
+        let mut broken = card("supply_drop");
+        broken.name = "broken_drop".to_string();
+        broken.effects = [("event".to_string(), "script draw(-1)".to_string()),
+            ("event".to_string(), "each player G0.5.0".to_string())].iter().cloned().collect();
+        let misc = parse_file("config/misc_cards.yaml".to_owned(), &Factions::core()).unwrap();
+        let cl = Rc::new(CardLibrary::new(vec![card("battle_pod"), card("blob_carrier"), broken], misc, vec![]).unwrap());
+        let mut game = GameState::new(Rc::clone(&cl));
+        let id = |name: &str| *cl.get_card_id(&name.to_string()).unwrap();
+        game.trade_row = Stack::new(vec![id("battle_pod")]);
+        game.trade_row_stack = Stack::new(vec![id("blob_carrier"), id("broken_drop")]);
+        game.acquire_from_trade_row(0, AcquireTo::Discard).unwrap();
+        assert_eq!(game.trade_row.elements, vec![id("blob_carrier")]);
+        assert_eq!(game.get_current_player().get_goods().authority, 55, "the rest of the event still happened");
+        let skipped: Vec<_> = game.take_events().into_iter().filter_map(|e| match e {
+            GameEvent::EventResolved { skipped, .. } => Some(skipped),
+            _ => None
+        }).collect();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].len(), 1);
+        assert!(skipped[0][0].contains("script draw(-1)"));
+    }
+
+    #[test]
+    fn test_missions () {
+        let mut game = game_with_sets(&["missions"]);
+        assert!(game.deal_missions(100).is_err());
+        game.deal_missions(3).unwrap();
+        assert_eq!(game.get_player(Player(1)).get_missions().len(), 3);
+
+        let mut game = game_with_sets(&["missions"]);
+        let mission = |name: &str| game.card_library.get_missions().iter()
+            .find(|m| m.name == name).unwrap().clone();
+        let (colonize, rule) = (mission("colonize"), mission("rule"));
+        let player = game.get_current_player_mut();
+        player.give_mission(colonize);
+        player.give_mission(rule.clone());
+        player.give_mission(rule.clone());
+        let base = (*game.card_library.get_card_by_name("port_of_call").unwrap()).clone();
+
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        assert!(!game.mission_objective_met(0));
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::RevealMission);
+        client.missions.borrow_mut().push_back(UserActionIntent::Continue(0));
+        game.advance(&client).unwrap();
+        assert!(game.get_current_player().get_completed_missions().is_empty());
+
+        // two bases in play
+        for _ in 0..2 {
+            let id = game.get_current_player_mut().give_card_to_hand(base.clone());
+            game.get_current_player_mut().get_card_in_hand_mut(&id).unwrap().1.reveal();
+        }
+        assert!(game.mission_objective_met(0));
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::RevealMission);
+        client.missions.borrow_mut().push_back(UserActionIntent::Continue(0));
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().trade, 3);
+        assert!(!game.can_undo(), "the mission was a secret");
+
+        // 60 authority, twice, is the third mission
+        let mut authority = get_good_action(Goods { trade: 0, authority: 10, combat: 0 });
+        assert!(matches!(authority(&mut game, &0, 0), Failure::Succeed));
+        for _ in 0..2 {
+            client.actions.borrow_mut().push_back(AbstractPlayerAction::RevealMission);
+            client.missions.borrow_mut().push_back(UserActionIntent::Continue(0));
+            game.advance(&client).unwrap();
+        }
+        assert_eq!(game.get_current_player().get_completed_missions().len(), MISSIONS_TO_WIN);
+        assert_eq!(game.winners(), Some(vec![Player(0)]));
+        // the raid boss starts with more than 60 authority, so it doesn't count for them
+        let cl = Rc::clone(&game.card_library);
+        let mut game = GameState::with_players(Rc::clone(&cl), 3, GameFormat::Raid).unwrap();
+        game.get_current_player_mut().give_mission(rule.clone());
+        assert!(!game.mission_objective_met(0));
+        // but raiders start with the usual 50
+        game.change_authority(Player(1), 10);
+        assert!(get_objective("60 authority").unwrap()(&game, Player(1)));
+
+        // a mission whose reward fails isn't completed
+        let mut game = GameState::new(cl);
+        game.get_current_player_mut().give_mission(Mission {
+            name: "broken".to_string(),
+            objective: "0 cards scrapped".to_string(),
+            reward: "script draw(-1)".to_string()
+        });
+        game.advance(&client).unwrap();
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::RevealMission);
+        client.missions.borrow_mut().push_back(UserActionIntent::Continue(0));
+        assert!(game.advance(&client).is_err());
+        assert_eq!(game.get_current_player().get_missions().len(), 1);
+        assert!(game.get_current_player().get_completed_missions().is_empty());
+    }
+
+    #[test]
+    fn test_colony_wars () {
+        let mut game = game_with_sets(&["colony_wars"]);
+        let cl = Rc::clone(&game.card_library);
+        let id = |name: &str| *cl.get_card_id(&name.to_string()).unwrap();
+        let card = |name: &str| (*cl.get_card_by_name(name).unwrap()).clone();
+        game.trade_row = Stack::new(vec![
+            id("warning_beacon"), id("frontier_station"), id("imperial_trader"), id("warning_beacon"), id("solar_skiff")
+        ]);
+        game.trade_row_stack = Stack::new(vec![id("cargo_pod"); 5]);
+        let hand_size = game.get_current_player().get_all_hand_card_ids().len();
+
+        // warning beacon only goes to the hand with a machine cult card in play
+        game.acquire_from_trade_row(0, AcquireTo::Discard).unwrap();
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand_size);
+        let drone = game.get_current_player_mut().give_card_to_hand(card("salvage_drone"));
+        game.get_current_player_mut().get_card_in_hand_mut(&drone).unwrap().1.reveal();
+        game.acquire_from_trade_row(2, AcquireTo::Discard).unwrap();
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand_size + 2);
+
+        // acquired effects happen right away
+        game.acquire_from_trade_row(1, AcquireTo::Discard).unwrap();
+        assert_eq!(game.get_current_player().get_goods().authority, 52);
+
+        // next acquire effects are used up by the next card
+        let (_, mut next_to_hand) = get_action(&"next acquire to hand".to_string()).unwrap();
+        assert!(matches!(next_to_hand(&mut game, &0, 0), Failure::Succeed));
+        game.acquire_from_trade_row(1, AcquireTo::Discard).unwrap();
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand_size + 3);
+        game.acquire_from_trade_row(1, AcquireTo::Discard).unwrap();
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand_size + 3);
+
+        // the reward only comes with a scrapped card
+        let (_, mut salvage) = get_action(&"scrap trade row for G0.0.2".to_string()).unwrap();
+        assert!(matches!(salvage(&mut game, &0, 0), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_goods().trade, 0);
+        assert!(matches!(salvage(&mut game, &0, 1), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_goods().trade, 2);
+        assert_eq!(game.get_scrap_heap().len(), 1);
+
+        // an acquired effect that can't happen is skipped, but the card is still acquired
+        let mut broken = card("cargo_pod");
+        broken.name = "broken_pod".to_string();
+        broken.effects = [("acquired".to_string(), "script draw(-1)".to_string())].iter().cloned().collect();
+        let misc = parse_file("config/misc_cards.yaml".to_owned(), &Factions::core()).unwrap();
+        let cl = Rc::new(CardLibrary::new(vec![broken.clone(), card("cargo_pod")], misc, vec![]).unwrap());
+        let mut game = GameState::new(Rc::clone(&cl));
+        let index = game.trade_row.iter().position(|id| cl.as_card(id).name == "broken_pod").unwrap();
+        let (acquired, skipped) = game.acquire_from_trade_row(index, AcquireTo::Discard).unwrap();
+        assert_eq!(acquired.name, "broken_pod");
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(game.get_current_player().cards_in_zone(Zone::Discard).len(), 1);
+        assert_eq!(game.trade_row.len(), 1, "the trade row is refilled with what there is");
+    }
+
+    #[test]
+    fn test_card_sets () {
+        let sets = CardLibrary::available_sets("config").unwrap();
+        let names: Vec<_> = sets.iter().map(|set| set.name.as_str()).collect();
+        assert!(names.contains(&"core") && names.contains(&"colony_wars") && names.contains(&"homebrew"));
+        // the default game is just the core set
+        let defaults: Vec<_> = sets.iter().filter(|set| set.default).map(|set| set.name.as_str()).collect();
+        assert_eq!(defaults, vec!["core"]);
+        let trade_cards = parse_file("config/trade_cards.yaml".to_owned(), &Factions::core()).unwrap();
+        assert_eq!(CardLibrary::from_config("config").unwrap().get_new_trade_stack().len(), trade_cards.len());
+
+        let core = CardLibrary::from_sets("config", &["core"]).unwrap();
+        assert_eq!(core.get_new_trade_stack().len(), trade_cards.len());
+        assert!(core.get_gambits().is_empty() && core.get_missions().is_empty());
+        let with_promos = CardLibrary::from_sets("config", &["core", "promos", "homebrew"]).unwrap();
+        assert!(with_promos.get_card_by_name("mega_mech").is_some());
+        assert!(with_promos.get_card_by_name("moonwurm").is_none());
+        for file in ["promos", "homebrew"].iter() {
+            for card in parse_file(format!("config/{}.yaml", file), with_promos.get_factions()).unwrap().iter() {
+                assert_validate_card_effects(card);
+            }
+        }
+        assert!(CardLibrary::from_sets("config", &["core", "nope"]).is_err());
+        assert!(CardLibrary::from_sets("config", &["promos"]).is_err(), "there's no scout");
+
+        // the same card in two sets
+        let dir = std::env::temp_dir().join(format!("star_realms_sets_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["trade_cards.yaml", "misc_cards.yaml"].iter() {
+            std::fs::copy(format!("config/{}", file), dir.join(file)).unwrap();
+        }
+        std::fs::write(dir.join("sets.yaml"), "\
+core:
+  trade: [trade_cards.yaml]
+  misc: [misc_cards.yaml]
+again:
+  trade: [trade_cards.yaml]
+").unwrap();
+        let result = CardLibrary::from_sets(dir.to_str().unwrap(), &["core", "again"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let err = result.err().expect("the sets conflict");
+        assert!(err.contains("'port_of_call' is in both core (trade_cards.yaml) and again (trade_cards.yaml)"), "{}", err);
+    }
+
+    #[test]
+    fn test_factions () {
+        let core = Factions::core();
+        // aliases mean the same faction, for synergy and effects alike
+        assert_eq!(core.parse("y"), Ok(Faction('s')));
+        assert_eq!(core.parse("f"), core.parse("t"));
+        assert!(core.parse("p").is_err());
+        assert_eq!(core.canonical(Faction('y')), Faction('s'));
+        assert_eq!(core.name(&Faction('b')), "Blob");
+        let yaml = YamlLoader::load_from_str("\
+card:
+  cost: 1
+  base: false
+  synergy:
+    - y
+        ").unwrap();
+        let card = parse_card("card", yaml[0]["card"].clone(), &core).unwrap();
+        assert_eq!(card.synergizes_with, [Faction('s')].iter().cloned().collect());
+
+        // the config's factions are the same as the built in ones
+        let cl = CardLibrary::from_sets("config", &["core"]).unwrap();
+        assert_eq!(cl.get_factions().all(), core.all());
+        assert_eq!(cl.get_factions().get(&Faction('s')), core.get(&Faction('s')));
+
+        // homebrew brings its own faction
+        let cl = Rc::new(CardLibrary::from_sets("config", &["core", "homebrew"]).unwrap());
+        assert_eq!(cl.get_factions().name(&Faction('p')), "Star Pirates");
+        assert!(CardLibrary::from_sets("config", &["homebrew"]).is_err(), "homebrew needs the core factions");
+        let mut game = GameState::with_players(Rc::clone(&cl), 2, GameFormat::FreeForAll).unwrap();
+        let raider = cl.as_new_card(cl.get_card_id(&"pirate_raider".to_string()).unwrap());
+        let player = game.get_current_player_mut();
+        let first = player.give_card_to_hand(raider.clone());
+        let second = player.give_card_to_hand(raider);
+        let mut ally = get_condition("syn-p".to_string()).unwrap();
+        assert!(!ally(&game, &second));
+        game.get_current_player_mut()
+            .use_effect(&first, &("free".to_string(), "G3.0.0".to_string())).unwrap();
+        assert!(ally(&game, &second));
+        // an unknown faction is never an ally
+        assert!(!get_condition("syn-q".to_string()).unwrap()(&game, &second));
+
+        // two factions can't share a code
+        let mut factions = Factions::core();
+        let info = core.get(&Faction('b')).unwrap().clone();
+        assert!(factions.add(FactionInfo { faction: Faction('y'), ..info }).is_err());
+    }
+
+    #[test]
+    fn test_scripts () {
+        let cl = Rc::new(CardLibrary::from_sets("config", &["core", "homebrew"]).unwrap());
+        for name in ["pirate_captain", "boarding_party"].iter() {
+            assert_validate_card_effects(&test_card(&cl, name));
+            assert!(cl.get_card_by_name(name).is_none(), "test cards aren't in the homebrew set");
+        }
+        let mut game = GameState::with_players(Rc::clone(&cl), 2, GameFormat::FreeForAll).unwrap();
+        let captain = test_card(&cl, "pirate_captain");
+        let id = game.get_current_player_mut().give_card_to_hand(captain);
+
+        // "draw" in a script doesn't make it the draw action
+        let (meta, mut action) = get_action(&"script gain(1, 2, 3); draw(2)".to_string()).unwrap();
+        assert!(meta.no_config());
+        let hand = game.get_current_player().get_all_hand_card_ids().len();
+        let goods = *game.get_current_player().get_goods();
+        assert!(matches!(action(&mut game, &id, 0), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand + 2);
+        assert_eq!(*game.get_current_player().get_goods(), goods + Goods { combat: 1, authority: 2, trade: 3 });
+
+        // scripts see the game, but only as it was before they ran
+        let hand = game.get_current_player().get_all_hand_card_ids().len();
+        let script = format!("script for i in 0..2 {{ if hand_size() == {} {{ draw(1) }} }}", hand);
+        let (_, mut action) = get_action(&script).unwrap();
+        assert!(matches!(action(&mut game, &id, 0), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand + 2);
+
+        // picking
+        let (meta, mut scrap) = get_action(&"script scrap(picked_card)".to_string()).unwrap();
+        assert!(meta.config.is_some());
+        assert!(matches!(scrap(&mut game, &id, 999), Failure::Fail(_)));
+        assert!(matches!(scrap(&mut game, &id, id), Failure::Succeed));
+        assert!(get_action(&"script scrap(picked_card); destroy_base(picked_base)".to_string()).is_none());
+
+        // if any change can't happen, none of them do
+        let (_, mut action) = get_action(&"script draw(1); gain(2, 0, 0); scrap(999)".to_string()).unwrap();
+        let hand = game.get_current_player().get_all_hand_card_ids().len();
+        let goods = *game.get_current_player().get_goods();
+        assert!(matches!(action(&mut game, &id, 0), Failure::Fail(_)));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand);
+        assert_eq!(*game.get_current_player().get_goods(), goods);
+
+        // the sandbox
+        for script in ["script loop {}", "script draw(1000)", "script draw(-1)", "script fn f() { f() } f()",
+                       "script import \"std\" as s;", "script eval(\"draw(1)\")"].iter() {
+            let hand = game.get_current_player().get_all_hand_card_ids().len();
+            if let Some((_, mut action)) = get_action(&script.to_string()) {
+                assert!(matches!(action(&mut game, &id, 0), Failure::Fail(_)), "{}", script);
+            }
+            assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand, "{}", script);
+        }
+        assert!(get_action(&"script draw(".to_string()).is_none());
+
+        // conditions
+        let authority = game.authority_of(Player(0));
+        let mut cond = get_condition(format!("script authority() == {} && has_ally(\"p\") == false", authority)).unwrap();
+        assert!(cond(&game, &id));
+        // actions aren't available to conditions, and a failed condition isn't met
+        assert!(!get_condition("script draw(1)".to_string()).unwrap()(&game, &id));
+        assert!(!get_condition("script 1 + 1".to_string()).unwrap()(&game, &id));
+        assert!(!get_condition("script cards_in_play(\"q\") == 0".to_string()).unwrap()(&game, &id));
+        assert!(get_condition("script (".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_compound_conditions () {
+        let cl = Rc::new(CardLibrary::from_sets("config", &["core", "homebrew"]).unwrap());
+        assert_validate_card_effects(&test_card(&cl, "corsair_fleet"));
+        let effects = cl.get_effects();
+        for cond in ["syn-b and scrap", "not (syn-b or syn-m)", "at most 0 ships in hand", "exactly 3 b outposts in deck"].iter() {
+            assert!(effects.get_condition(cond).is_some(), "{}", cond);
+        }
+        for cond in ["syn-b and", "not", "(syn-b", "syn-b or acquired", "at least 2 bases in the bin", "at least x cards in play"].iter() {
+            assert!(effects.get_condition(cond).is_none(), "{}", cond);
+        }
+        assert!(is_trash_cond(&"syn-b and scrap".to_string()));
+        assert!(is_trash_cond(&"syn-b and (scrap and syn-m)".to_string()));
+        for cond in ["syn-b and not free", "not scrap", "syn-b or scrap", "syn-b and (scrap or syn-m)", "not (syn-b and scrap)"].iter() {
+            assert!(!is_trash_cond(&cond.to_string()), "{}", cond);
+        }
+
+        let mut game = GameState::with_players(Rc::clone(&cl), 2, GameFormat::FreeForAll).unwrap();
+        let fleet = test_card(&cl, "corsair_fleet");
+        let id = game.get_current_player_mut().give_card_to_hand(fleet);
+        let check = |game: &GameState, cond: &str| cl.get_effects().get_condition(cond).unwrap()(game, &id);
+        assert!(check(&game, "at least 1 p card in hand"));
+        assert!(check(&game, "exactly 0 cards played this turn"));
+        assert!(check(&game, "not at least 1 base in play and not syn-p"));
+        assert!(!check(&game, "at least 1 q card in hand"));
+
+        // playing it moves it between zones
+        let player = game.get_current_player_mut();
+        player.get_card_mut(&id).unwrap().1.in_play = true;
+        player.use_effect(&id, &("free".to_string(), "G4.0.0".to_string())).unwrap();
+        assert!(check(&game, "exactly 1 p ship in play and exactly 1 card played this turn"));
+        assert!(check(&game, "at most 0 p cards in hand"));
+        // it doesn't count as its own ally, but it does count itself
+        assert!(!check(&game, "at least 2 p cards played this turn or syn-p"));
+        assert!(check(&game, "not syn-p and (syn-b or at least 1 p card played this turn)"));
+    }
+
+    #[test]
+    fn test_scaled_actions () {
+        let cl = Rc::new(CardLibrary::from_sets("config", &["core", "homebrew"]).unwrap());
+        for name in ["brood_world", "imperial_bastion"].iter() {
+            assert_validate_card_effects(&test_card(&cl, name));
+        }
+        for action in ["draw for each b card in play", "scrap a card in hand for each base in play",
+                       "G0.0.1 for each card in hand"].iter() {
+            assert!(get_action(&action.to_string()).is_none(), "{}", action);
+        }
+        let mut game = GameState::with_players(Rc::clone(&cl), 2, GameFormat::FreeForAll).unwrap();
+        let card = |name: &str| cl.as_new_card(cl.get_card_id(&name.to_string()).unwrap());
+        let play = |game: &mut GameState, id: HandId| {
+            let player = game.get_current_player_mut();
+            player.get_card_mut(&id).unwrap().1.in_play = true;
+            player.use_effect(&id, &("free".to_string(), "test".to_string())).unwrap();
+        };
+        let world = game.get_current_player_mut().give_card_to_hand(test_card(&cl, "brood_world"));
+        let carrier = game.get_current_player_mut().give_card_to_hand(card("blob_carrier"));
+        let bastion = game.get_current_player_mut().give_card_to_hand(test_card(&cl, "imperial_bastion"));
+        for id in [world, carrier, bastion].iter() {
+            play(&mut game, *id);
+        }
+        let player = game.get_current_player();
+        assert_eq!(player.counter(Counter::FactionPlayed(Faction('b'))), 2);
+        assert_eq!(player.counter(Counter::FactionPlayed(Faction('s'))), 1);
+        assert_eq!(player.counter(Counter::BasesInPlay), 2);
+        assert_eq!(player.counter(Counter::ShipsInPlay), 1);
+
+        let hand = game.get_current_player().get_all_hand_card_ids().len();
+        let (_, mut draw) = get_action(&"draw for each b card played this turn".to_string()).unwrap();
+        assert!(matches!(draw(&mut game, &world, 0), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand + 2);
+        let goods = *game.get_current_player().get_goods();
+        let (_, mut gain) = get_action(&"G1.0.2 for each base in play".to_string()).unwrap();
+        assert!(matches!(gain(&mut game, &bastion, 0), Failure::Succeed));
+        assert_eq!(*game.get_current_player().get_goods(), goods + Goods { combat: 2, authority: 0, trade: 4 });
+        // an unknown faction can't be counted
+        let (_, mut unknown) = get_action(&"draw for each q card played this turn".to_string()).unwrap();
+        assert!(matches!(unknown(&mut game, &world, 0), Failure::Fail(_)));
+
+        // gaining a faction counts, leaving play doesn't
+        let (_, mut add_faction) = get_action(&"add faction s".to_string()).unwrap();
+        assert!(matches!(add_faction(&mut game, &carrier, 0), Failure::Succeed));
+        assert_eq!(game.get_current_player().counter(Counter::FactionPlayed(Faction('s'))), 2);
+        game.get_current_player_mut().discard_by_id(&carrier);
+        assert_eq!(game.get_current_player().counter(Counter::ShipsInPlay), 0);
+        assert_eq!(game.get_current_player().counter(Counter::FactionPlayed(Faction('b'))), 1);
+        // the bases are still in play next turn, but nothing has been played
+        game.get_current_player_mut().end_turn();
+        assert_eq!(game.get_current_player().counter(Counter::BasesInPlay), 2);
+        assert_eq!(game.get_current_player().counter(Counter::FactionPlayed(Faction('b'))), 0);
+    }
+
+    #[test]
+    fn test_deck_inspection () {
+        let cl = Rc::new(CardLibrary::from_sets("config", &["core", "homebrew"]).unwrap());
+        for name in ["long_range_scanner", "quartermaster"].iter() {
+            assert_validate_card_effects(&test_card(&cl, name));
+        }
+        for action in ["look at top 0", "look at top 9", "look at top", "search deck for", "search deck for ships"].iter() {
+            assert!(get_action(&action.to_string()).is_none(), "{}", action);
+        }
+        assert_eq!(decode_arrangement(encode_arrangement(&[2, 0, 7])), vec![2, 0, 7]);
+        assert_eq!(decode_arrangement(encode_arrangement(&[])), Vec::<usize>::new());
+
+        let mut game = GameState::with_players(Rc::clone(&cl), 2, GameFormat::FreeForAll).unwrap();
+        let scanner = test_card(&cl, "long_range_scanner");
+        let id = game.get_current_player_mut().give_card_to_hand(scanner);
+        let names = |game: &GameState, n: usize| -> Vec<String> {
+            game.get_current_player().top_of_deck(n).iter().map(|c| c.name.clone()).collect()
+        };
+        // the rest of the starting deck, after the first hand
+        let top = names(&game, 10);
+        assert!(top.len() >= 5);
+
+        // put the third card on top of the first, discard the second
+        let (meta, mut look) = get_action(&"look at top 3".to_string()).unwrap();
+        assert!(matches!(meta.config.unwrap().config_method, ActionConfigMethod::ArrangeTopOfDeck(3, _)));
+        for bad in [encode_arrangement(&[0, 0]), encode_arrangement(&[3])].iter() {
+            assert!(matches!(look(&mut game, &id, *bad), Failure::Fail(_)));
+            assert_eq!(names(&game, 10), top);
+        }
+        assert!(matches!(look(&mut game, &id, encode_arrangement(&[2, 0])), Failure::Succeed));
+        let arranged = names(&game, 10);
+        assert_eq!(arranged[..2], [top[2].clone(), top[0].clone()]);
+        assert_eq!(arranged[2..], top[3..]);
+        // and everything can be discarded
+        assert!(matches!(look(&mut game, &id, 0), Failure::Succeed));
+        assert_eq!(names(&game, 10), arranged[3..]);
+
+        // searching: the starting deck has no bases
+        let (_, mut search) = get_action(&"search deck for base".to_string()).unwrap();
+        let hand = game.get_current_player().get_all_hand_card_ids().len();
+        assert!(game.deck_choices(&RelativePlayer::Current, &CardFilter { bases_only: true, ..CardFilter::any() }).is_empty());
+        assert!(matches!(search(&mut game, &id, 7), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand);
+        let (_, mut search) = get_action(&"search deck for ship".to_string()).unwrap();
+        let deck = names(&game, 10).len();
+        let choices = game.deck_choices(&RelativePlayer::Current, &CardFilter { ships_only: true, ..CardFilter::any() });
+        // starting from the top
+        assert_eq!(choices, (0..deck).rev().collect::<Vec<_>>());
+        assert!(matches!(search(&mut game, &id, deck as u32), Failure::Fail(_)));
+        assert!(matches!(search(&mut game, &id, 0), Failure::Succeed));
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), hand + 1);
+        assert_eq!(names(&game, 10).len(), deck - 1);
+
+        // looking at more cards than are left shuffles the discard pile in under them, like drawing
+        let left = names(&game, 10);
+        let discarded = game.get_current_player().cards_in_zone(Zone::Discard).len();
+        assert!(left.len() < 3 && left.len() + discarded >= 3);
+        assert!(matches!(look(&mut game, &id, encode_arrangement(&[0, 1, 2])), Failure::Succeed));
+        assert_eq!(names(&game, 10).len(), left.len() + discarded);
+        assert_eq!(names(&game, 10)[..left.len()], left[..]);
+        assert!(game.get_current_player().cards_in_zone(Zone::Discard).is_empty());
+    }
+
+    #[test]
+    fn test_effect_registry () {
+        let mut cl = CardLibrary::from_config("config").unwrap();
+        let effects = cl.get_effects();
+        // an effect goes to the longest keyword it starts with
+        assert!(effects.get_action("scrap trade row for G0.0.1").unwrap().0.description.contains("then"));
+        assert!(effects.get_action("scrap trade row").is_some());
+        assert!(effects.get_action("drawing").is_none());
+        assert!(effects.get_condition("synergy").is_none());
+
+        // homebrew effects
+        let yaml = YamlLoader::load_from_str("\
+card:
+  cost: 1
+  base: false
+  effects:
+    - big-hand: tithe 2
+        ").unwrap();
+        let card = parse_card("card", yaml[0]["card"].clone(), cl.get_factions()).unwrap();
+        assert!(cl.get_effects().validate_card_effects(&card).is_some());
+        let effects = cl.get_effects_mut();
+        effects.register_action("tithe", |name: &str, effects: &EffectRegistry| {
+            let n: Authority = name.strip_prefix("tithe ")?.parse().ok()?;
+            // built out of a built in action
+            let (_, mut gain) = effects.get_action(&format!("G0.{}.0", n))?;
+            let meta = ActionMeta { description: format!("Gain {} authority", n), config: None };
+            let action: ActionFunc = Box::new(move |game: &mut GameState, id: &HandId, cfg| gain(game, id, cfg));
+            Some((meta, action))
+        }).unwrap();
+        effects.register_condition("big-hand", |_: &str, _: &EffectRegistry| {
+            let cond: ConditionFunc = Box::new(|game: &GameState, _: &HandId| {
+                game.get_current_player().get_all_hand_card_ids().len() >= 4
+            });
+            Some(cond)
+        }).unwrap();
+        assert!(effects.register_action("draw", |_: &str, _: &EffectRegistry| None).is_err());
+        assert_eq!(cl.get_effects().validate_card_effects(&card), None);
+        // the built in effects are still there, nested effects can use the new ones
+        assert!(cl.get_effects().get_action("each player tithe 1").is_some());
+        assert!(cl.get_effects().get_action("tithe").is_none());
+
+        // the game uses its library's effects
+        let mut game = GameState::new(Rc::new(cl));
+        let client = ScriptedClient::default();
+        game.advance(&client).unwrap();
+        let id = game.get_current_player_mut().give_card_to_hand(card);
+        assert_eq!(game.get_current_player().get_all_hand_card_ids().len(), 4);
+        let authority = game.get_current_player().get_goods().authority;
+        client.actions.borrow_mut().push_back(AbstractPlayerAction::CardEffects);
+        client.effects.borrow_mut().push_back(
+            UserActionIntent::Continue((id, ("big-hand".to_string(), "tithe 2".to_string()))));
+        game.advance(&client).unwrap();
+        assert_eq!(game.get_current_player().get_goods().authority, authority + 2);
+    }
+
     #[test]
     fn test_sizes () {
         println!("Size of String: {}", mem::size_of::<String>());
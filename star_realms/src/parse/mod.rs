@@ -3,15 +3,17 @@ extern crate regex;
 use std::collections::{HashSet};
 use std::fs;
 
-use crate::game::components::card::{Base, Card};
-use crate::game::components::faction::Faction;
+use crate::game::components::card::{Base, Card, CardKind};
+use crate::game::components::faction::{Faction, FactionInfo, Factions};
+use crate::game::components::mission::Mission;
+use crate::game::card_library::CardSet;
 
 use self::yaml_rust::{Yaml, YamlLoader};
 use self::regex::Regex;
 use crate::game::Goods;
-use crate::game::components::Coin;
+use crate::game::components::{Coin, Defense};
 
-pub fn parse_file (filepath: String) -> Result<Vec<Card>, String> {
+pub fn parse_file (filepath: String, factions: &Factions) -> Result<Vec<Card>, String> {
     let contents = fs::read_to_string(filepath);
     match contents {
         Ok(contents) => {
@@ -27,7 +29,7 @@ pub fn parse_file (filepath: String) -> Result<Vec<Card>, String> {
                                 match key {
                                     None => return Err("key is not a string".to_string()),
                                     Some(key) => {
-                                        match parse_card(key, v.clone()) {
+                                        match parse_card(key, v.clone(), factions) {
                                             Ok(nice) => cards.push(nice),
                                             Err(e) => return Err(
                                                 format!("error on card '{}': {}", key, e))
@@ -47,13 +49,144 @@ pub fn parse_file (filepath: String) -> Result<Vec<Card>, String> {
     }
 }
 
-pub fn parse_card (name: &str, yaml: Yaml) -> Result<Card, String> {
+pub fn parse_missions_file (filepath: String) -> Result<Vec<Mission>, String> {
+    let contents = fs::read_to_string(filepath)
+        .map_err(|e| format!("error reading file: {}", e))?;
+    let yaml = YamlLoader::load_from_str(&contents)
+        .map_err(|e| format!("scan error: {}", e))?;
+    match &yaml[0] {
+        Yaml::Hash(b) => {
+            let mut missions = vec![];
+            for (k, v) in b {
+                let key = k.as_str().ok_or("key is not a string".to_string())?;
+                let mission = parse_mission(key, v)
+                    .map_err(|e| format!("error on mission '{}': {}", key, e))?;
+                missions.push(mission);
+            }
+            Ok(missions)
+        }
+        _ => Err("must be a hash".to_string())
+    }
+}
+
+/// see `config/factions.yaml`
+pub fn parse_factions_file (filepath: String) -> Result<Vec<FactionInfo>, String> {
+    let contents = fs::read_to_string(filepath)
+        .map_err(|e| format!("error reading file: {}", e))?;
+    let yaml = YamlLoader::load_from_str(&contents)
+        .map_err(|e| format!("scan error: {}", e))?;
+    match &yaml[0] {
+        Yaml::Hash(b) => {
+            let mut factions = vec![];
+            for (k, v) in b {
+                let key = k.as_str().ok_or("key is not a string".to_string())?;
+                let faction = parse_faction(key, v)
+                    .map_err(|e| format!("error on faction '{}': {}", key, e))?;
+                factions.push(faction);
+            }
+            Ok(factions)
+        }
+        _ => Err("must be a hash".to_string())
+    }
+}
+
+/// the key is the faction's name
+pub fn parse_faction (name: &str, yaml: &Yaml) -> Result<FactionInfo, String> {
+    // codes are single letters, so effects like "syn-b" can tell where they end
+    let letter = |s: &str| -> Result<char, String> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_alphanumeric() => Ok(c),
+            _ => Err(format!("'{}' is not a single letter", s))
+        }
+    };
+    let code = yaml["code"].as_str()
+        .ok_or("must supply a 'code' (string)".to_string())?;
+    let aliases = match &yaml["aliases"] {
+        Yaml::BadValue => vec![],
+        Yaml::Array(aliases) => aliases.iter()
+            .map(|a| a.as_str().ok_or("'aliases' must be a list of strings".to_string()).and_then(letter))
+            .collect::<Result<_, _>>()?,
+        _ => return Err("'aliases' must be a list of strings".to_string())
+    };
+    let colour = yaml["colour"].as_str()
+        .ok_or("must supply a 'colour' (string)".to_string())?;
+    Ok(FactionInfo {
+        faction: Faction(letter(code)?),
+        name: name.to_owned(),
+        aliases,
+        colour: colour.to_owned(),
+    })
+}
+
+/// see `config/sets.yaml`
+pub fn parse_set_manifest (filepath: String) -> Result<Vec<CardSet>, String> {
+    let contents = fs::read_to_string(filepath)
+        .map_err(|e| format!("error reading file: {}", e))?;
+    let yaml = YamlLoader::load_from_str(&contents)
+        .map_err(|e| format!("scan error: {}", e))?;
+    match &yaml[0] {
+        Yaml::Hash(b) => {
+            let mut sets = vec![];
+            for (k, v) in b {
+                let name = k.as_str().ok_or("key is not a string".to_string())?;
+                let set = parse_card_set(name, v)
+                    .map_err(|e| format!("error on set '{}': {}", name, e))?;
+                sets.push(set);
+            }
+            Ok(sets)
+        }
+        _ => Err("must be a hash".to_string())
+    }
+}
+
+pub fn parse_card_set (name: &str, yaml: &Yaml) -> Result<CardSet, String> {
+    // every list of files is optional
+    let files = |key: &str| -> Result<Vec<String>, String> {
+        match &yaml[key] {
+            Yaml::BadValue => Ok(vec![]),
+            Yaml::Array(files) => files.iter()
+                .map(|f| f.as_str().map(|f| f.to_owned()).ok_or(format!("'{}' must be a list of file names", key)))
+                .collect(),
+            _ => Err(format!("'{}' must be a list of file names", key))
+        }
+    };
+    let default = match &yaml["default"] {
+        Yaml::BadValue => false,
+        Yaml::Boolean(default) => *default,
+        _ => return Err("'default' must be a bool".to_string())
+    };
+    Ok(CardSet {
+        name: name.to_owned(),
+        default,
+        trade: files("trade")?,
+        misc: files("misc")?,
+        gambits: files("gambits")?,
+        missions: files("missions")?,
+        factions: files("factions")?,
+    })
+}
+
+pub fn parse_mission (name: &str, yaml: &Yaml) -> Result<Mission, String> {
+    let objective = yaml["objective"].as_str()
+        .ok_or("must supply an 'objective' (string)".to_string())?;
+    let reward = yaml["reward"].as_str()
+        .ok_or("must supply a 'reward' (string)".to_string())?;
+    Ok(Mission {
+        name: name.to_owned(),
+        objective: objective.to_owned(),
+        reward: reward.to_owned(),
+    })
+}
+
+pub fn parse_card (name: &str, yaml: Yaml, factions: &Factions) -> Result<Card, String> {
     let obj = yaml;
     let base = match obj["base"].as_bool() {
         Some(_base) => match _base {
             true => {
                 let defense = match obj["defense"].as_i64() {
-                    Some(_defense) => _defense as u8,
+                    Some(_defense) if 0 <= _defense && _defense <= Defense::MAX as i64 => _defense as Defense,
+                    Some(x) => return Err(format!("{} is not a valid 'defense'", x)),
                     None => return Err("must supply a 'defense' (int) value if 'base' is true".to_string())
                 };
                 match obj["outpost"].as_bool() {
@@ -69,9 +202,21 @@ pub fn parse_card (name: &str, yaml: Yaml) -> Result<Card, String> {
         None => return Err("must supply 'base'".to_string())
     };
 
+    // 'hero' and 'event' are optional, most cards are neither
+    let mut kind = CardKind::Deck;
+    for (key, special) in [("hero", CardKind::Hero), ("event", CardKind::Event)].iter() {
+        match (obj[*key].as_bool(), obj[*key].is_badvalue()) {
+            (Some(true), _) if base.is_some() => return Err(format!("a {} cannot be a base", key)),
+            (Some(true), _) if kind != CardKind::Deck => return Err("a card can only be one kind".to_string()),
+            (Some(true), _) => kind = *special,
+            (Some(false), _) | (None, true) => (),
+            (None, false) => return Err(format!("'{}' must be a bool", key))
+        }
+    }
+
     let cost = match obj["cost"].as_i64() {
-        Some(_cost) if (0 <= _cost && _cost <= 255) => _cost as Coin,
-        Some(x) => return Err(format!("{} is not in the range 0..255 for coins", x)),
+        Some(_cost) if (0 <= _cost && _cost <= Coin::MAX as i64) => _cost as Coin,
+        Some(x) => return Err(format!("{} is not in the range 0..{} for coins", x, Coin::MAX)),
         None => return Err("must supply 'cost'".to_string())
     };
 
@@ -89,12 +234,9 @@ pub fn parse_card (name: &str, yaml: Yaml) -> Result<Card, String> {
     if let Some(synergy) = obj["synergy"].as_vec() {
         for syn in synergy {
             if let Some(syn) = syn.as_str() {
-                match syn {
-                    "m" => synergizes_with.insert(Faction::Mech),
-                    "s" => synergizes_with.insert(Faction::Star),
-                    "b" => synergizes_with.insert(Faction::Blob),
-                    "f" | "t" => synergizes_with.insert(Faction::Fed), // f or t for Trade Federation
-                    _ => return Err(format!("synergy symbol '{}' was not one of [m, s, b, f]", syn)),
+                match factions.parse(syn) {
+                    Ok(faction) => synergizes_with.insert(faction),
+                    Err(e) => return Err(format!("bad synergy symbol: {}", e)),
                 };
             } else {
                 return Err("synergy could not be a string".to_string())
@@ -126,6 +268,7 @@ pub fn parse_card (name: &str, yaml: Yaml) -> Result<Card, String> {
         cost,
         name: name.to_owned(),
         base,
+        kind,
         synergizes_with,
         effects,
     })
@@ -146,11 +289,11 @@ pub fn parse_goods(good_str: &str) -> Option<Goods> {
             if let Some(c) = caps.get(1) {
                 if let Some(a) = caps.get(2) {
                     if let Some(t) = caps.get(3) {
+                        // the regex makes sure these are numbers, but they could be too big
                         Some(Goods {
-                            // unwrapping because we used regex to get strings
-                            combat: c.as_str().parse().unwrap(),
-                            authority: a.as_str().parse().unwrap(),
-                            trade: t.as_str().parse().unwrap()
+                            combat: c.as_str().parse().ok()?,
+                            authority: a.as_str().parse().ok()?,
+                            trade: t.as_str().parse().ok()?
                         })
                     } else {
                         None